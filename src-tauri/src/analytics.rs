@@ -0,0 +1,305 @@
+use rusqlite::params_from_iter;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::Database;
+
+// Common filters for sales queries. `start_date` is inclusive and `end_date`
+// exclusive, and both accept either a date ("2024-01-31"), taken as local
+// midnight like the report buckets, or a full RFC 3339 timestamp.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SalesFilter {
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub cash_register_id: Option<String>,
+}
+
+// One row of a grouped sales query
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SalesBucket {
+    pub key: String,
+    pub label: String,
+    pub transaction_count: i64,
+    pub quantity: f64,
+    pub total: f64,
+}
+
+// SQL for a date bound as a UTC datetime. Plain dates are local days, the same
+// days the buckets group by, so they are shifted from local time to UTC.
+fn date_bound(value: &str, index: usize) -> String {
+    if value.contains('T') {
+        format!("datetime(?{})", index)
+    } else {
+        format!("datetime(?{}, 'utc')", index)
+    }
+}
+
+// Build the WHERE clause for a filter against the `transactions` alias `t`
+fn filter_clause(filter: &SalesFilter) -> (String, Vec<String>) {
+    // Voided sales never count towards sales figures
+    let mut conditions = vec!["t.voided_at IS NULL".to_string()];
+    let mut values = Vec::new();
+
+    if let Some(start_date) = &filter.start_date {
+        values.push(start_date.clone());
+        conditions.push(format!("datetime(t.timestamp) >= {}", date_bound(start_date, values.len())));
+    }
+    if let Some(end_date) = &filter.end_date {
+        values.push(end_date.clone());
+        conditions.push(format!("datetime(t.timestamp) < {}", date_bound(end_date, values.len())));
+    }
+    if let Some(register_id) = &filter.cash_register_id {
+        values.push(register_id.clone());
+        conditions.push(format!("t.cash_register_id = ?{}", values.len()));
+    }
+
+    (conditions.join(" AND "), values)
+}
+
+fn query_buckets(db: &State<Database>, sql: &str, values: Vec<String>) -> Result<Vec<SalesBucket>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+
+    let bucket_iter = stmt.query_map(params_from_iter(values), |row| {
+        Ok(SalesBucket {
+            key: row.get(0)?,
+            label: row.get(1)?,
+            transaction_count: row.get(2)?,
+            quantity: row.get(3)?,
+            total: row.get(4)?,
+        })
+    }).map_err(|e| e.to_string())?;
+
+    let mut buckets = Vec::new();
+    for bucket in bucket_iter {
+        buckets.push(bucket.map_err(|e| e.to_string())?);
+    }
+
+    Ok(buckets)
+}
+
+// Transaction-level aggregation; item quantities come from a per-transaction subquery
+// so that joining items does not multiply the transaction totals
+fn transaction_buckets(db: &State<Database>, key_expr: &str, label_expr: &str, joins: &str, filter: &SalesFilter) -> Result<Vec<SalesBucket>, String> {
+    let (where_clause, values) = filter_clause(filter);
+    let sql = format!(
+        "SELECT {key} AS bucket, {label}, COUNT(t.id), COALESCE(SUM(q.quantity), 0), COALESCE(SUM(t.total), 0.0)
+         FROM transactions t
+         LEFT JOIN (SELECT transaction_id, SUM(quantity) AS quantity FROM transaction_items GROUP BY transaction_id) q
+             ON q.transaction_id = t.id
+         {joins}
+         WHERE {where_clause}
+         GROUP BY bucket
+         ORDER BY bucket",
+        key = key_expr,
+        label = label_expr,
+        joins = joins,
+        where_clause = where_clause,
+    );
+
+    query_buckets(db, &sql, values)
+}
+
+// Sales grouped by local hour of day ("00".."23")
+#[tauri::command]
+pub fn get_sales_by_hour(filter: SalesFilter, db: State<Database>) -> Result<Vec<SalesBucket>, String> {
+    transaction_buckets(
+        &db,
+        "strftime('%H', t.timestamp, 'localtime')",
+        "strftime('%H', t.timestamp, 'localtime') || ':00'",
+        "",
+        &filter,
+    )
+}
+
+// Sales grouped by local day, ISO-like week or month
+#[tauri::command]
+pub fn get_sales_by_period(period: String, filter: SalesFilter, db: State<Database>) -> Result<Vec<SalesBucket>, String> {
+    let format = match period.as_str() {
+        "day" => "%Y-%m-%d",
+        "week" => "%Y-W%W",
+        "month" => "%Y-%m",
+        _ => return Err(format!("Unknown period: {}", period)),
+    };
+    let key_expr = format!("strftime('{}', t.timestamp, 'localtime')", format);
+
+    transaction_buckets(&db, &key_expr, &key_expr, "", &filter)
+}
+
+// Sales per cashier, using the user recorded on the transaction
+#[tauri::command]
+pub fn get_sales_by_cashier(filter: SalesFilter, db: State<Database>) -> Result<Vec<SalesBucket>, String> {
+    transaction_buckets(
+        &db,
+        "COALESCE(t.user_id, '')",
+        "COALESCE(u.full_name, 'Unassigned')",
+        "LEFT JOIN users u ON u.id = t.user_id",
+        &filter,
+    )
+}
+
+// Item-level sales grouped by products.category
+#[tauri::command]
+pub fn get_sales_by_category(filter: SalesFilter, db: State<Database>) -> Result<Vec<SalesBucket>, String> {
+    let (where_clause, values) = filter_clause(&filter);
+    let sql = format!(
+        "SELECT COALESCE(p.category, 'Uncategorized') AS bucket, COALESCE(p.category, 'Uncategorized'),
+                COUNT(DISTINCT t.id), COALESCE(SUM(ti.quantity), 0), COALESCE(SUM(ti.subtotal), 0.0)
+         FROM transaction_items ti
+         JOIN transactions t ON t.id = ti.transaction_id
+         LEFT JOIN products p ON p.id = ti.product_id
+         WHERE {}
+         GROUP BY bucket
+         ORDER BY SUM(ti.subtotal) DESC",
+        where_clause
+    );
+
+    query_buckets(&db, &sql, values)
+}
+
+// Top-N products ranked by "quantity" or "revenue"
+#[tauri::command]
+pub fn get_top_products(rank_by: String, limit: Option<i64>, filter: SalesFilter, db: State<Database>) -> Result<Vec<SalesBucket>, String> {
+    let order = match rank_by.as_str() {
+        "quantity" => "SUM(ti.quantity) DESC, SUM(ti.subtotal) DESC",
+        "revenue" => "SUM(ti.subtotal) DESC, SUM(ti.quantity) DESC",
+        _ => return Err(format!("Unknown ranking: {}", rank_by)),
+    };
+
+    let (where_clause, mut values) = filter_clause(&filter);
+    values.push(limit.unwrap_or(10).max(1).to_string());
+    let sql = format!(
+        "SELECT ti.product_id AS bucket, MAX(ti.name),
+                COUNT(DISTINCT t.id), COALESCE(SUM(ti.quantity), 0), COALESCE(SUM(ti.subtotal), 0.0)
+         FROM transaction_items ti
+         JOIN transactions t ON t.id = ti.transaction_id
+         WHERE {}
+         GROUP BY bucket
+         ORDER BY {}
+         LIMIT CAST(?{} AS INTEGER)",
+        where_clause,
+        order,
+        values.len()
+    );
+
+    query_buckets(&db, &sql, values)
+}
+
+// Item-level sales with variants rolled up into their parent product;
+// products without variants are listed on their own
+#[tauri::command]
+pub fn get_sales_by_parent_product(filter: SalesFilter, db: State<Database>) -> Result<Vec<SalesBucket>, String> {
+    let (where_clause, values) = filter_clause(&filter);
+    let sql = format!(
+        "SELECT COALESCE(p.parent_id, ti.product_id) AS bucket, COALESCE(MAX(pp.name), MAX(ti.name)),
+                COUNT(DISTINCT t.id), COALESCE(SUM(ti.quantity), 0), COALESCE(SUM(ti.subtotal), 0.0)
+         FROM transaction_items ti
+         JOIN transactions t ON t.id = ti.transaction_id
+         LEFT JOIN products p ON p.id = ti.product_id
+         LEFT JOIN parent_products pp ON pp.id = p.parent_id
+         WHERE {}
+         GROUP BY bucket
+         ORDER BY SUM(ti.subtotal) DESC",
+        where_clause
+    );
+
+    query_buckets(&db, &sql, values)
+}
+
+// Tendered sales per payment method, grouped by the payment method registry.
+// Sales recorded before tender lines existed count under their payment method.
+#[tauri::command]
+pub fn get_sales_by_payment_method(filter: SalesFilter, db: State<Database>) -> Result<Vec<SalesBucket>, String> {
+    let (where_clause, values) = filter_clause(&filter);
+    let sql = format!(
+        "SELECT tenders.method AS bucket, COALESCE(pm.display_name, tenders.method),
+                COUNT(DISTINCT tenders.transaction_id), 0, COALESCE(SUM(tenders.amount), 0.0)
+         FROM (
+             SELECT p.transaction_id, p.method, p.amount
+             FROM payments p
+             JOIN transactions t ON t.id = p.transaction_id
+             WHERE {where_clause}
+             UNION ALL
+             SELECT t.id, t.payment_method, t.total
+             FROM transactions t
+             WHERE {where_clause} AND NOT EXISTS (SELECT 1 FROM payments p WHERE p.transaction_id = t.id)
+         ) tenders
+         LEFT JOIN payment_methods pm ON pm.code = tenders.method
+         GROUP BY bucket
+         ORDER BY COALESCE(pm.sort_order, 999), bucket",
+        where_clause = where_clause,
+    );
+
+    query_buckets(&db, &sql, values)
+}
+
+// One row of a margin report. Revenue is net of discounts and tax; cost uses
+// the unit cost stamped on each line when it was sold.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MarginBucket {
+    pub key: String,
+    pub label: String,
+    pub quantity: f64,
+    pub revenue: f64,
+    pub cost: f64,
+    pub gross_margin: f64,
+    // Gross margin as a percentage of revenue
+    pub margin_percent: f64,
+}
+
+// Gross margin grouped by "product", "category", "cashier", "day", "week" or "month"
+#[tauri::command]
+pub fn get_margin_report(group_by: String, filter: SalesFilter, db: State<Database>) -> Result<Vec<MarginBucket>, String> {
+    let (key_expr, label_expr) = match group_by.as_str() {
+        "product" => ("ti.product_id", "MAX(ti.name)"),
+        "category" => ("COALESCE(p.category, 'Uncategorized')", "COALESCE(p.category, 'Uncategorized')"),
+        "cashier" => ("COALESCE(t.user_id, '')", "COALESCE(u.full_name, 'Unassigned')"),
+        "day" => ("strftime('%Y-%m-%d', t.timestamp, 'localtime')", "strftime('%Y-%m-%d', t.timestamp, 'localtime')"),
+        "week" => ("strftime('%Y-W%W', t.timestamp, 'localtime')", "strftime('%Y-W%W', t.timestamp, 'localtime')"),
+        "month" => ("strftime('%Y-%m', t.timestamp, 'localtime')", "strftime('%Y-%m', t.timestamp, 'localtime')"),
+        _ => return Err(format!("Unknown grouping: {}", group_by)),
+    };
+
+    // Lines from before tax tracking have no taxable amount; their subtotal is the revenue
+    let (where_clause, values) = filter_clause(&filter);
+    let sql = format!(
+        "SELECT {key} AS bucket, {label}, COALESCE(SUM(ti.quantity), 0),
+                COALESCE(SUM(CASE WHEN ti.taxable_amount = 0 AND ti.tax_amount = 0 THEN ti.subtotal ELSE ti.taxable_amount END), 0.0),
+                COALESCE(SUM(ti.unit_cost * ti.quantity), 0.0)
+         FROM transaction_items ti
+         JOIN transactions t ON t.id = ti.transaction_id
+         LEFT JOIN products p ON p.id = ti.product_id
+         LEFT JOIN users u ON u.id = t.user_id
+         WHERE {where_clause}
+         GROUP BY bucket
+         ORDER BY bucket",
+        key = key_expr,
+        label = label_expr,
+        where_clause = where_clause,
+    );
+
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+
+    let bucket_iter = stmt.query_map(params_from_iter(values), |row| {
+        let revenue = crate::pricing::round_money(row.get(3)?);
+        let cost = crate::pricing::round_money(row.get(4)?);
+        let gross_margin = crate::pricing::round_money(revenue - cost);
+        Ok(MarginBucket {
+            key: row.get(0)?,
+            label: row.get(1)?,
+            quantity: row.get(2)?,
+            revenue,
+            cost,
+            gross_margin,
+            margin_percent: if revenue != 0.0 { (gross_margin / revenue * 10000.0).round() / 100.0 } else { 0.0 },
+        })
+    }).map_err(|e| e.to_string())?;
+
+    let mut buckets = Vec::new();
+    for bucket in bucket_iter {
+        buckets.push(bucket.map_err(|e| e.to_string())?);
+    }
+
+    Ok(buckets)
+}
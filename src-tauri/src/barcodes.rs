@@ -0,0 +1,320 @@
+// Alternate barcodes and pack sizes.
+//
+// Besides its own `products.barcode`, a product can have any number of extra
+// barcodes in `product_barcodes`: supplier codes that sell a single unit, or
+// pack and carton codes that sell `pack_quantity` units at an optional pack
+// price. A barcode belongs to exactly one product across both tables.
+//
+// Barcodes are validated when saved: numeric codes of EAN-8, UPC-A and EAN-13
+// length must carry a correct check digit, anything else must be encodable as
+// Code128. Items without a manufacturer barcode get an in-store EAN-13 from
+// the `internal_barcode_prefix` range.
+
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::Database;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductBarcode {
+    pub barcode: String,
+    pub product_id: String,
+    pub pack_quantity: i32,
+    // Price of the whole pack; None sells it at the unit price times the quantity
+    pub pack_price: Option<f64>,
+    pub label: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductBarcodeInput {
+    pub barcode: String,
+    #[serde(default = "default_pack_quantity")]
+    pub pack_quantity: i32,
+    pub pack_price: Option<f64>,
+    pub label: Option<String>,
+}
+
+fn default_pack_quantity() -> i32 {
+    1
+}
+
+const BARCODE_COLUMNS: &str = "barcode, product_id, pack_quantity, pack_price, label, created_at";
+
+fn map_barcode(row: &rusqlite::Row) -> rusqlite::Result<ProductBarcode> {
+    Ok(ProductBarcode {
+        barcode: row.get(0)?,
+        product_id: row.get(1)?,
+        pack_quantity: row.get(2)?,
+        pack_price: row.get(3)?,
+        label: row.get(4)?,
+        created_at: row.get(5)?,
+    })
+}
+
+// Longest code accepted as Code128; longer symbols do not fit a shelf label
+const CODE128_MAX_LENGTH: usize = 48;
+
+// GS1 check digit (EAN-8, UPC-A, EAN-13): weights alternate 3 and 1 from the
+// rightmost data digit
+fn has_valid_check_digit(barcode: &str) -> bool {
+    if barcode.len() < 2 || !barcode.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+    let digits: Vec<u32> = barcode.chars().filter_map(|c| c.to_digit(10)).collect();
+    let (data, check) = digits.split_at(digits.len() - 1);
+    let sum: u32 = data.iter().rev().enumerate().map(|(i, d)| if i % 2 == 0 { d * 3 } else { *d }).sum();
+    (10 - sum % 10) % 10 == check[0]
+}
+
+fn check_digit(data: &str) -> u32 {
+    (0..10).find(|d| has_valid_check_digit(&format!("{}{}", data, d))).unwrap_or(0)
+}
+
+// EAN-13 with a correct check digit
+pub fn is_valid_ean13(barcode: &str) -> bool {
+    barcode.len() == 13 && has_valid_check_digit(barcode)
+}
+
+// The symbology a barcode is saved as: "EAN-8", "UPC-A", "EAN-13" or "Code128"
+pub fn barcode_format(barcode: &str) -> Result<&'static str, String> {
+    if barcode.is_empty() {
+        return Err("Barcode is required".to_string());
+    }
+
+    if barcode.chars().all(|c| c.is_ascii_digit()) {
+        let format = match barcode.len() {
+            8 => Some("EAN-8"),
+            12 => Some("UPC-A"),
+            13 => Some("EAN-13"),
+            _ => None,
+        };
+        if let Some(format) = format {
+            if !has_valid_check_digit(barcode) {
+                return Err(format!("{} is not a valid {}: wrong check digit", barcode, format));
+            }
+            return Ok(format);
+        }
+    }
+
+    if barcode.len() > CODE128_MAX_LENGTH {
+        return Err(format!("Barcode is longer than {} characters", CODE128_MAX_LENGTH));
+    }
+    // Code128 encodes printable ASCII
+    if !barcode.chars().all(|c| c.is_ascii_graphic() || c == ' ') {
+        return Err(format!("{} contains characters Code128 cannot encode", barcode));
+    }
+
+    Ok("Code128")
+}
+
+// Whether a barcode is taken by any product, as main or alternate barcode
+pub fn barcode_in_use(conn: &Connection, barcode: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM products WHERE barcode = ?1) OR EXISTS (SELECT 1 FROM product_barcodes WHERE barcode = ?1)",
+        params![barcode],
+        |row| row.get(0)
+    ).map_err(|e| e.to_string())
+}
+
+pub fn validate_internal_prefix(prefix: &str) -> Result<(), String> {
+    if prefix.len() < 2 || prefix.len() > 6 || !prefix.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("Internal barcode prefix {} must be 2 to 6 digits", prefix));
+    }
+
+    Ok(())
+}
+
+pub fn internal_prefix(conn: &Connection) -> Result<String, String> {
+    Ok(crate::get_setting(conn, "internal_barcode_prefix")?
+        .and_then(|value| value.as_str().map(|s| s.to_string()))
+        .unwrap_or_else(|| "29".to_string()))
+}
+
+// The next free in-store EAN-13: the prefix, a running number and the check
+// digit. Numbers continue after the highest one issued and wrap around to fill
+// gaps once the range is exhausted. The prefix is kept apart from scale label
+// prefixes, so a generated code is never read as a scale label.
+pub fn next_internal_barcode(conn: &Connection) -> Result<String, String> {
+    let prefix = internal_prefix(conn)?;
+    validate_internal_prefix(&prefix)?;
+    crate::scale::check_prefixes(&crate::scale::scale_label_layouts(conn)?, &prefix)?;
+
+    let width = 12 - prefix.len();
+    let limit = 10u64.pow(width as u32);
+    let highest: Option<i64> = conn.query_row(
+        "SELECT MAX(CAST(SUBSTR(barcode, ?2, ?3) AS INTEGER)) FROM (
+             SELECT barcode FROM products UNION ALL SELECT barcode FROM product_barcodes
+         ) WHERE LENGTH(barcode) = 13 AND SUBSTR(barcode, 1, ?1) = ?4",
+        params![prefix.len() as i64, prefix.len() as i64 + 1, width as i64, prefix],
+        |row| row.get(0)
+    ).map_err(|e| e.to_string())?;
+    let start = highest.map(|n| n as u64 + 1).unwrap_or(1) % limit;
+
+    for number in (start..limit).chain(1..start) {
+        let data = format!("{}{:0width$}", prefix, number, width = width);
+        let barcode = format!("{}{}", data, check_digit(&data));
+        if !barcode_in_use(conn, &barcode)? {
+            return Ok(barcode);
+        }
+    }
+
+    Err(format!("No free internal barcodes left with prefix {}", prefix))
+}
+
+pub fn find_barcode(conn: &Connection, barcode: &str) -> Result<Option<ProductBarcode>, String> {
+    match conn.query_row(
+        &format!("SELECT {} FROM product_barcodes WHERE barcode = ?1", BARCODE_COLUMNS),
+        params![barcode],
+        map_barcode
+    ) {
+        Ok(barcode) => Ok(Some(barcode)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// Units of stock one sold item takes when it was scanned with `barcode`. The
+// product's own barcode, or no barcode at all, is a single unit.
+pub fn pack_quantity(conn: &Connection, product_id: &str, barcode: Option<&str>) -> Result<i32, String> {
+    let barcode = match barcode {
+        Some(barcode) => barcode,
+        None => return Ok(1),
+    };
+
+    match find_barcode(conn, barcode)? {
+        Some(pack) if pack.product_id == product_id => Ok(pack.pack_quantity),
+        Some(_) => Err(format!("Barcode {} belongs to another product", barcode)),
+        None => Ok(1),
+    }
+}
+
+// Shelf price of one item scanned with `barcode`: the pack price for pack
+// barcodes (or the unit price times the pack size), otherwise the product's
+// price, which is per kg for weighed products
+pub fn shelf_price(conn: &Connection, product_id: &str, barcode: Option<&str>) -> Result<f64, String> {
+    let price: f64 = conn.query_row(
+        "SELECT price FROM products WHERE id = ?1",
+        params![product_id],
+        |row| row.get(0)
+    ).map_err(|_| format!("Product {} not found", product_id))?;
+
+    match barcode.map(|barcode| find_barcode(conn, barcode)).transpose()?.flatten() {
+        Some(pack) if pack.product_id == product_id && pack.pack_quantity > 1 => Ok(pack.pack_price.unwrap_or_else(|| crate::pricing::round_money(price * pack.pack_quantity as f64))),
+        _ => Ok(price),
+    }
+}
+
+#[tauri::command]
+pub fn get_product_barcodes(product_id: String, db: State<Database>) -> Result<Vec<ProductBarcode>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM product_barcodes WHERE product_id = ?1 ORDER BY pack_quantity, barcode",
+        BARCODE_COLUMNS
+    )).map_err(|e| e.to_string())?;
+
+    let barcode_iter = stmt.query_map(params![product_id], map_barcode).map_err(|e| e.to_string())?;
+
+    let mut barcodes = Vec::new();
+    for barcode in barcode_iter {
+        barcodes.push(barcode.map_err(|e| e.to_string())?);
+    }
+
+    Ok(barcodes)
+}
+
+#[tauri::command]
+pub fn add_product_barcode(product_id: String, barcode: ProductBarcodeInput, db: State<Database>) -> Result<ProductBarcode, String> {
+    let code = barcode.barcode.trim().to_string();
+    barcode_format(&code)?;
+    if barcode.pack_quantity < 1 {
+        return Err("Pack quantity must be at least 1".to_string());
+    }
+    if let Some(price) = barcode.pack_price {
+        if !price.is_finite() || price < 0.0 {
+            return Err("Pack price cannot be negative".to_string());
+        }
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM products WHERE id = ?1)",
+        params![product_id],
+        |row| row.get(0)
+    ).map_err(|e| e.to_string())?;
+    if !exists {
+        return Err(format!("Product {} not found", product_id));
+    }
+    if barcode_in_use(&conn, &code)? {
+        return Err(format!("Barcode {} is already in use", code));
+    }
+
+    conn.execute(
+        "INSERT INTO product_barcodes (barcode, product_id, pack_quantity, pack_price, label, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![code, product_id, barcode.pack_quantity, barcode.pack_price, barcode.label.map(|l| l.trim().to_string()).filter(|l| !l.is_empty()), Utc::now().to_rfc3339()]
+    ).map_err(|e| e.to_string())?;
+
+    find_barcode(&conn, &code)?.ok_or_else(|| "Barcode not found".to_string())
+}
+
+#[tauri::command]
+pub fn remove_product_barcode(barcode: String, db: State<Database>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let removed = conn.execute("DELETE FROM product_barcodes WHERE barcode = ?1", params![barcode.trim()]).map_err(|e| e.to_string())?;
+    if removed == 0 {
+        return Err(format!("Barcode {} not found", barcode.trim()));
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BarcodeCheck {
+    pub barcode: String,
+    pub format: String,
+    pub in_use: bool,
+}
+
+// Validate a barcode before it is saved, e.g. while typing it into a product form
+#[tauri::command]
+pub fn validate_barcode(barcode: String, db: State<Database>) -> Result<BarcodeCheck, String> {
+    let barcode = barcode.trim().to_string();
+    let format = barcode_format(&barcode)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    Ok(BarcodeCheck {
+        in_use: barcode_in_use(&conn, &barcode)?,
+        format: format.to_string(),
+        barcode,
+    })
+}
+
+// Preview the next in-store barcode; it is only reserved once a product uses it
+#[tauri::command]
+pub fn generate_internal_barcode(db: State<Database>) -> Result<String, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    next_internal_barcode(&conn)
+}
+
+// Replace a product's own barcode with a new in-store one, for items that came
+// without a manufacturer barcode
+#[tauri::command]
+pub fn assign_internal_barcode(product_id: String, db: State<Database>) -> Result<String, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let barcode = next_internal_barcode(&conn)?;
+    let updated = conn.execute(
+        "UPDATE products SET barcode = ?1 WHERE id = ?2",
+        params![barcode, product_id]
+    ).map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err(format!("Product {} not found", product_id));
+    }
+
+    Ok(barcode)
+}
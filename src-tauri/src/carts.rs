@@ -0,0 +1,216 @@
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::pricing::DiscountInput;
+use crate::{Database, LoginData, TransactionItem};
+
+// A cart put aside at a register. Parked carts only hold the cart contents;
+// stock, receipt numbers and payments are untouched until the cart is resumed
+// and completed through create_transaction.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ParkedCart {
+    pub id: String,
+    pub cash_register_id: String,
+    pub user_id: String,
+    pub shift_id: Option<String>,
+    pub label: Option<String>,
+    pub items: Vec<TransactionItem>,
+    pub discount: Option<DiscountInput>,
+    pub customer_id: Option<String>,
+    pub total: f64,
+    pub created_at: String,
+    pub expires_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ParkCartInput {
+    pub user_id: String,
+    pub shift_id: Option<String>,
+    pub cash_register_id: Option<String>,
+    pub label: Option<String>,
+    pub items: Vec<TransactionItem>,
+    pub discount: Option<DiscountInput>,
+    pub customer_id: Option<String>,
+}
+
+// Remove carts past their expiry time
+pub fn purge_expired_carts(conn: &Connection) -> Result<usize, String> {
+    conn.execute(
+        "DELETE FROM parked_carts WHERE expires_at <= ?1",
+        params![Utc::now().to_rfc3339()]
+    ).map_err(|e| e.to_string())
+}
+
+fn map_parked_cart(row: &rusqlite::Row) -> rusqlite::Result<(ParkedCart, String, Option<String>)> {
+    Ok((
+        ParkedCart {
+            id: row.get(0)?,
+            cash_register_id: row.get(1)?,
+            user_id: row.get(2)?,
+            shift_id: row.get(3)?,
+            label: row.get(4)?,
+            items: Vec::new(),
+            discount: None,
+            customer_id: row.get(7)?,
+            total: row.get(8)?,
+            created_at: row.get(9)?,
+            expires_at: row.get(10)?,
+        },
+        row.get(5)?,
+        row.get(6)?,
+    ))
+}
+
+// Items and discount are stored as JSON; decode them into the cart
+fn decode_cart((mut cart, items, discount): (ParkedCart, String, Option<String>)) -> Result<ParkedCart, String> {
+    cart.items = serde_json::from_str(&items).map_err(|e| e.to_string())?;
+    cart.discount = match discount {
+        Some(discount) => Some(serde_json::from_str(&discount).map_err(|e| e.to_string())?),
+        None => None,
+    };
+    Ok(cart)
+}
+
+const CART_COLUMNS: &str = "id, cash_register_id, user_id, shift_id, label, items, discount, customer_id, total, created_at, expires_at";
+
+#[tauri::command]
+pub fn park_cart(cart: ParkCartInput, db: State<Database>) -> Result<String, String> {
+    if cart.items.is_empty() {
+        return Err("Cannot park an empty cart".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    // Carts belong to the shift's register when parked during a shift
+    let cash_register_id: String = match (&cart.shift_id, &cart.cash_register_id) {
+        (Some(shift_id), _) => conn.query_row(
+            "SELECT cash_register_id FROM cash_shifts WHERE id = ?1 AND status = 'open'",
+            params![shift_id],
+            |row| row.get(0)
+        ).map_err(|_| "Shift is not open".to_string())?,
+        (None, Some(register_id)) => register_id.clone(),
+        (None, None) => conn.query_row(
+            "SELECT id FROM cash_registers WHERE is_active = 1 LIMIT 1",
+            [],
+            |row| row.get(0)
+        ).map_err(|e| e.to_string())?,
+    };
+
+    let ttl_minutes = crate::setting_f64(&conn, "parked_cart_ttl_minutes", 240.0)?;
+    let now = Utc::now();
+    let expires_at = now + Duration::minutes(ttl_minutes.max(1.0) as i64);
+
+    let total = crate::pricing::round_money(cart.items.iter().map(|i| i.price * i.quantity).sum());
+    let items = serde_json::to_string(&cart.items).map_err(|e| e.to_string())?;
+    let discount = match &cart.discount {
+        Some(discount) => Some(serde_json::to_string(discount).map_err(|e| e.to_string())?),
+        None => None,
+    };
+
+    let cart_id = Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO parked_carts (id, cash_register_id, user_id, shift_id, label, items, discount, customer_id, total, created_at, expires_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![cart_id, cash_register_id, cart.user_id, cart.shift_id, cart.label, items, discount, cart.customer_id, total, now.to_rfc3339(), expires_at.to_rfc3339()]
+    ).map_err(|e| e.to_string())?;
+
+    Ok(cart_id)
+}
+
+// Parked carts at a register, optionally only those of one cashier; oldest first
+#[tauri::command]
+pub fn list_parked_carts(cash_register_id: String, user_id: Option<String>, db: State<Database>) -> Result<Vec<ParkedCart>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    purge_expired_carts(&conn)?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM parked_carts
+         WHERE cash_register_id = ?1 AND (?2 IS NULL OR user_id = ?2)
+         ORDER BY created_at",
+        CART_COLUMNS
+    )).map_err(|e| e.to_string())?;
+
+    let cart_iter = stmt.query_map(params![cash_register_id, user_id], map_parked_cart).map_err(|e| e.to_string())?;
+
+    let mut carts = Vec::new();
+    for cart in cart_iter {
+        carts.push(decode_cart(cart.map_err(|e| e.to_string())?)?);
+    }
+
+    Ok(carts)
+}
+
+// Only the cashier who parked a cart may take it over, unless a manager approves
+fn check_cart_owner(conn: &Connection, owner: &str, user_id: &str, approval: Option<&LoginData>) -> Result<(), String> {
+    if owner == user_id {
+        return Ok(());
+    }
+
+    match approval {
+        Some(approval) => crate::verify_manager_approval(conn, approval).map(|_| ()),
+        None => Err("Parked cart belongs to another cashier and requires manager approval".to_string()),
+    }
+}
+
+// Take a parked cart back into the sale screen. The cart is removed so it can
+// only be resumed once; discount approvals are not stored and must be given again.
+// A cart can only be resumed by the cashier who parked it unless a manager approves.
+#[tauri::command]
+pub fn resume_cart(cart_id: String, cash_register_id: String, user_id: String, approval: Option<LoginData>, db: State<Database>) -> Result<ParkedCart, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let cart = match conn.query_row(
+        &format!("SELECT {} FROM parked_carts WHERE id = ?1", CART_COLUMNS),
+        params![cart_id],
+        map_parked_cart
+    ) {
+        Ok(cart) => decode_cart(cart)?,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Err("Parked cart not found".to_string()),
+        Err(e) => return Err(e.to_string()),
+    };
+
+    if cart.cash_register_id != cash_register_id {
+        return Err("Parked cart belongs to another register".to_string());
+    }
+
+    check_cart_owner(&conn, &cart.user_id, &user_id, approval.as_ref())?;
+
+    let expired = DateTime::parse_from_rfc3339(&cart.expires_at)
+        .map(|expires_at| expires_at <= Utc::now())
+        .unwrap_or(true);
+    if expired {
+        purge_expired_carts(&conn)?;
+        return Err("Parked cart has expired".to_string());
+    }
+
+    conn.execute("DELETE FROM parked_carts WHERE id = ?1", params![cart_id]).map_err(|e| e.to_string())?;
+
+    Ok(cart)
+}
+
+// Discard a parked cart; as with resuming, another cashier's cart needs a
+// manager's approval
+#[tauri::command]
+pub fn delete_parked_cart(cart_id: String, user_id: String, approval: Option<LoginData>, db: State<Database>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let owner: String = match conn.query_row(
+        "SELECT user_id FROM parked_carts WHERE id = ?1",
+        params![cart_id],
+        |row| row.get(0)
+    ) {
+        Ok(owner) => owner,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Err("Parked cart not found".to_string()),
+        Err(e) => return Err(e.to_string()),
+    };
+    check_cart_owner(&conn, &owner, &user_id, approval.as_ref())?;
+
+    let deleted = conn.execute("DELETE FROM parked_carts WHERE id = ?1", params![cart_id]).map_err(|e| e.to_string())?;
+    if deleted == 0 {
+        return Err("Parked cart not found".to_string());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,246 @@
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::{Database, TransactionPage, TransactionSearch};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Customer {
+    pub id: String,
+    pub name: String,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    // NPWP or other tax registration number, printed on tax invoices
+    pub tax_id: Option<String>,
+    pub notes: Option<String>,
+    pub is_member: bool,
+    pub is_active: bool,
+    pub created_at: String,
+    pub updated_at: String,
+    // Maximum outstanding balance for sales on account; 0 means no credit
+    pub credit_limit: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustomerInput {
+    pub name: String,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub tax_id: Option<String>,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub is_member: bool,
+}
+
+// Purchase totals for a customer with one page of their transactions
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustomerPurchaseHistory {
+    pub customer: Customer,
+    pub transaction_count: i64,
+    pub total_spent: f64,
+    pub last_purchase_at: Option<String>,
+    pub transactions: TransactionPage,
+}
+
+const CUSTOMER_COLUMNS: &str = "id, name, phone, email, tax_id, notes, is_member, is_active, created_at, updated_at, credit_limit";
+
+// Phone numbers are stored as digits in local form ("+62 812-3456" -> "08123456")
+// so that lookups match however the number was typed
+pub fn normalize_phone(phone: &str) -> String {
+    let digits: String = phone.chars().filter(|c| c.is_ascii_digit()).collect();
+    match digits.strip_prefix("62") {
+        Some(rest) if phone.trim_start().starts_with('+') || rest.starts_with('8') => format!("0{}", rest),
+        _ => digits,
+    }
+}
+
+fn clean(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+fn map_customer(row: &rusqlite::Row) -> rusqlite::Result<Customer> {
+    Ok(Customer {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        phone: row.get(2)?,
+        email: row.get(3)?,
+        tax_id: row.get(4)?,
+        notes: row.get(5)?,
+        is_member: row.get(6)?,
+        is_active: row.get(7)?,
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+        credit_limit: row.get(10)?,
+    })
+}
+
+fn query_customers(conn: &Connection, sql: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Vec<Customer>, String> {
+    let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+    let customer_iter = stmt.query_map(params, map_customer).map_err(|e| e.to_string())?;
+
+    let mut customers = Vec::new();
+    for customer in customer_iter {
+        customers.push(customer.map_err(|e| e.to_string())?);
+    }
+
+    Ok(customers)
+}
+
+pub fn find_customer(conn: &Connection, customer_id: &str) -> Result<Option<Customer>, String> {
+    match conn.query_row(
+        &format!("SELECT {} FROM customers WHERE id = ?1", CUSTOMER_COLUMNS),
+        params![customer_id],
+        map_customer
+    ) {
+        Ok(customer) => Ok(Some(customer)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// Whether member-only promotions apply to a sale for this customer (if any)
+pub fn is_member(conn: &Connection, customer_id: Option<&str>) -> Result<bool, String> {
+    match customer_id {
+        Some(customer_id) => Ok(find_customer(conn, customer_id)?
+            .filter(|customer| customer.is_active)
+            .ok_or_else(|| "Customer not found".to_string())?
+            .is_member),
+        None => Ok(false),
+    }
+}
+
+fn validate_customer(customer: &CustomerInput) -> Result<(), String> {
+    if customer.name.trim().is_empty() {
+        return Err("Customer name is required".to_string());
+    }
+    if let Some(email) = clean(customer.email.clone()) {
+        if !email.contains('@') {
+            return Err(format!("Invalid email address: {}", email));
+        }
+    }
+    Ok(())
+}
+
+// Active customers, optionally filtered by name
+#[tauri::command]
+pub fn get_customers(query: Option<String>, db: State<Database>) -> Result<Vec<Customer>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let pattern = format!("%{}%", query.unwrap_or_default().trim());
+
+    query_customers(
+        &conn,
+        &format!("SELECT {} FROM customers WHERE is_active = 1 AND name LIKE ?1 ORDER BY name LIMIT 200", CUSTOMER_COLUMNS),
+        &[&pattern]
+    )
+}
+
+#[tauri::command]
+pub fn get_customer(customer_id: String, db: State<Database>) -> Result<Option<Customer>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    find_customer(&conn, &customer_id)
+}
+
+// Customers whose phone number contains the given digits
+#[tauri::command]
+pub fn search_customers_by_phone(phone: String, db: State<Database>) -> Result<Vec<Customer>, String> {
+    let digits = normalize_phone(&phone);
+    if digits.len() < 3 {
+        return Ok(Vec::new());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    query_customers(
+        &conn,
+        &format!(
+            "SELECT {} FROM customers
+             WHERE is_active = 1 AND phone LIKE ?1
+             ORDER BY phone = ?2 DESC, name
+             LIMIT 20",
+            CUSTOMER_COLUMNS
+        ),
+        &[&format!("%{}%", digits), &digits]
+    )
+}
+
+#[tauri::command]
+pub fn create_customer(customer: CustomerInput, db: State<Database>) -> Result<Customer, String> {
+    validate_customer(&customer)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let customer_id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    let phone = clean(customer.phone).map(|p| normalize_phone(&p));
+
+    conn.execute(
+        "INSERT INTO customers (id, name, phone, email, tax_id, notes, is_member, is_active, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 1, ?8, ?8)",
+        params![customer_id, customer.name.trim(), phone, clean(customer.email), clean(customer.tax_id), clean(customer.notes), customer.is_member, now]
+    ).map_err(|e| e.to_string())?;
+
+    find_customer(&conn, &customer_id)?.ok_or_else(|| "Customer not found".to_string())
+}
+
+#[tauri::command]
+pub fn update_customer(customer_id: String, customer: CustomerInput, db: State<Database>) -> Result<Customer, String> {
+    validate_customer(&customer)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let phone = clean(customer.phone).map(|p| normalize_phone(&p));
+
+    let updated = conn.execute(
+        "UPDATE customers SET name = ?1, phone = ?2, email = ?3, tax_id = ?4, notes = ?5, is_member = ?6, updated_at = ?7
+         WHERE id = ?8",
+        params![customer.name.trim(), phone, clean(customer.email), clean(customer.tax_id), clean(customer.notes), customer.is_member, Utc::now().to_rfc3339(), customer_id]
+    ).map_err(|e| e.to_string())?;
+
+    if updated == 0 {
+        return Err("Customer not found".to_string());
+    }
+
+    find_customer(&conn, &customer_id)?.ok_or_else(|| "Customer not found".to_string())
+}
+
+// Customers are deactivated rather than deleted so their sales keep the link
+#[tauri::command]
+pub fn delete_customer(customer_id: String, db: State<Database>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "UPDATE customers SET is_active = 0, updated_at = ?1 WHERE id = ?2",
+        params![Utc::now().to_rfc3339(), customer_id]
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+// Lifetime totals for a customer (voided sales excluded) and their
+// transactions, newest first, paged like search_transactions
+#[tauri::command]
+pub fn get_customer_purchase_history(customer_id: String, cursor: Option<String>, limit: Option<i64>, db: State<Database>) -> Result<CustomerPurchaseHistory, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let customer = find_customer(&conn, &customer_id)?.ok_or_else(|| "Customer not found".to_string())?;
+
+    let (transaction_count, total_spent, last_purchase_at): (i64, f64, Option<String>) = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(total), 0.0), MAX(timestamp) FROM transactions WHERE customer_id = ?1 AND voided_at IS NULL",
+        params![customer_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+    ).map_err(|e| e.to_string())?;
+
+    let transactions = crate::query_transactions(&conn, TransactionSearch {
+        customer_id: Some(customer_id),
+        cursor,
+        limit,
+        ..Default::default()
+    })?;
+
+    Ok(CustomerPurchaseHistory {
+        customer,
+        transaction_count,
+        total_spent: crate::pricing::round_money(total_spent),
+        last_purchase_at,
+        transactions,
+    })
+}
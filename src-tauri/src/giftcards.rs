@@ -0,0 +1,344 @@
+// Gift cards and store credit.
+//
+// Both are stored value cards identified by a unique code: gift cards are sold
+// at the counter, store credit is issued when a sale is voided and refunded to
+// credit. A card's balance is kept on the card row and every change is also
+// written to `gift_card_ledger` (issue, redeem, refund and adjust entries), so
+// the ledger is the card's full history.
+//
+// Cards are redeemed as tenders ("gift_card" or "store_credit") with the card
+// code as the payment reference. Redemption runs inside the sale's database
+// transaction while `Database`'s connection lock is held, and the balance is
+// only taken with a conditional update, so two sales can never spend the same
+// balance.
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::payments::Payment;
+use crate::pricing::round_money;
+use crate::{Database, LoginData};
+
+pub const GIFT_CARD_METHOD: &str = "gift_card";
+pub const STORE_CREDIT_METHOD: &str = "store_credit";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GiftCard {
+    pub id: String,
+    pub code: String,
+    // "gift_card" or "store_credit"
+    pub card_type: String,
+    pub customer_id: Option<String>,
+    pub initial_balance: f64,
+    pub balance: f64,
+    pub is_active: bool,
+    pub expires_at: Option<String>,
+    pub issued_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GiftCardEntry {
+    pub id: String,
+    pub gift_card_id: String,
+    pub transaction_id: Option<String>,
+    pub entry_type: String,
+    pub amount: f64,
+    pub balance_after: f64,
+    pub payment_method: Option<String>,
+    pub note: Option<String>,
+    pub user_id: Option<String>,
+    pub created_at: String,
+}
+
+// A gift card sold at the counter. Without a code one is generated.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GiftCardSale {
+    pub code: Option<String>,
+    pub amount: f64,
+    pub payment_method: String,
+    pub customer_id: Option<String>,
+    pub expires_at: Option<String>,
+    pub shift_id: Option<String>,
+    pub user_id: String,
+}
+
+const CARD_COLUMNS: &str = "id, code, card_type, customer_id, initial_balance, balance, is_active, expires_at, issued_at";
+
+fn map_card(row: &rusqlite::Row) -> rusqlite::Result<GiftCard> {
+    Ok(GiftCard {
+        id: row.get(0)?,
+        code: row.get(1)?,
+        card_type: row.get(2)?,
+        customer_id: row.get(3)?,
+        initial_balance: row.get(4)?,
+        balance: row.get(5)?,
+        is_active: row.get(6)?,
+        expires_at: row.get(7)?,
+        issued_at: row.get(8)?,
+    })
+}
+
+// Codes are compared without spaces or dashes and in upper case
+fn normalize_code(code: &str) -> String {
+    code.chars().filter(|c| c.is_ascii_alphanumeric()).collect::<String>().to_uppercase()
+}
+
+pub fn find_card(conn: &Connection, code: &str) -> Result<Option<GiftCard>, String> {
+    match conn.query_row(
+        &format!("SELECT {} FROM gift_cards WHERE code = ?1", CARD_COLUMNS),
+        params![normalize_code(code)],
+        map_card
+    ) {
+        Ok(card) => Ok(Some(card)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn generate_code(conn: &Connection) -> Result<String, String> {
+    loop {
+        let code = Uuid::new_v4().simple().to_string()[..16].to_uppercase();
+        if find_card(conn, &code)?.is_none() {
+            return Ok(code);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn insert_entry(
+    conn: &Connection,
+    gift_card_id: &str,
+    transaction_id: Option<&str>,
+    entry_type: &str,
+    amount: f64,
+    payment_method: Option<&str>,
+    note: Option<&str>,
+    user_id: Option<&str>,
+) -> Result<(), String> {
+    let balance_after: f64 = conn.query_row(
+        "SELECT balance FROM gift_cards WHERE id = ?1",
+        params![gift_card_id],
+        |row| row.get(0)
+    ).map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "INSERT INTO gift_card_ledger (id, gift_card_id, transaction_id, entry_type, amount, balance_after, payment_method, note, user_id, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![Uuid::new_v4().to_string(), gift_card_id, transaction_id, entry_type, amount, balance_after, payment_method, note, user_id, Utc::now().to_rfc3339()]
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+// Create a card with its opening balance
+#[allow(clippy::too_many_arguments)]
+pub fn issue_card(
+    conn: &Connection,
+    code: Option<&str>,
+    card_type: &str,
+    amount: f64,
+    customer_id: Option<&str>,
+    expires_at: Option<&str>,
+    transaction_id: Option<&str>,
+    payment_method: Option<&str>,
+    user_id: Option<&str>,
+) -> Result<GiftCard, String> {
+    let amount = round_money(amount);
+    if amount <= 0.0 || !amount.is_finite() {
+        return Err("Card amount must be positive".to_string());
+    }
+
+    let code = match code.map(normalize_code).filter(|c| !c.is_empty()) {
+        Some(code) => {
+            if find_card(conn, &code)?.is_some() {
+                return Err(format!("Card code {} is already in use", code));
+            }
+            code
+        }
+        None => generate_code(conn)?,
+    };
+
+    let card_id = Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO gift_cards (id, code, card_type, customer_id, initial_balance, balance, is_active, expires_at, issued_at, issued_by)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?5, 1, ?6, ?7, ?8)",
+        params![card_id, code, card_type, customer_id, amount, expires_at, Utc::now().to_rfc3339(), user_id]
+    ).map_err(|e| e.to_string())?;
+
+    insert_entry(conn, &card_id, transaction_id, "issue", amount, payment_method, None, user_id)?;
+
+    find_card(conn, &code)?.ok_or_else(|| "Card not found".to_string())
+}
+
+// Take card tenders of a sale off their cards. The payment reference is the card code.
+pub fn redeem_tenders(conn: &Connection, transaction_id: &str, payments: &[Payment], user_id: Option<&str>) -> Result<(), String> {
+    for payment in payments.iter().filter(|p| p.method == GIFT_CARD_METHOD || p.method == STORE_CREDIT_METHOD) {
+        let code = payment.reference.as_deref().unwrap_or("");
+        let card = find_card(conn, code)?.ok_or_else(|| format!("Card {} not found", code))?;
+
+        if !card.is_active || card.card_type != payment.method {
+            return Err(format!("Card {} cannot be used for this payment", card.code));
+        }
+        let expired = card.expires_at.as_deref()
+            .and_then(|expires_at| DateTime::parse_from_rfc3339(expires_at).ok())
+            .map(|expires_at| expires_at <= Utc::now())
+            .unwrap_or(false);
+        if expired {
+            return Err(format!("Card {} has expired", card.code));
+        }
+
+        let updated = conn.execute(
+            "UPDATE gift_cards SET balance = ROUND(balance - ?1, 2) WHERE id = ?2 AND balance >= ?1 - 0.005",
+            params![payment.amount, card.id]
+        ).map_err(|e| e.to_string())?;
+        if updated == 0 {
+            return Err(format!("Card {} has a balance of {:.2}", card.code, card.balance));
+        }
+
+        insert_entry(conn, &card.id, Some(transaction_id), "redeem", -payment.amount, None, None, user_id)?;
+    }
+
+    Ok(())
+}
+
+// Put card redemptions of a voided sale back on their cards
+pub fn refund_redemptions(conn: &Connection, transaction_id: &str, note: &str, user_id: Option<&str>) -> Result<(), String> {
+    let mut stmt = conn.prepare(
+        "SELECT gift_card_id, SUM(amount) FROM gift_card_ledger
+         WHERE transaction_id = ?1 AND entry_type IN ('redeem', 'refund')
+         GROUP BY gift_card_id"
+    ).map_err(|e| e.to_string())?;
+
+    let redemptions = stmt.query_map(params![transaction_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    for (gift_card_id, amount) in redemptions {
+        if amount < 0.0 {
+            conn.execute(
+                "UPDATE gift_cards SET balance = ROUND(balance - ?1, 2) WHERE id = ?2",
+                params![amount, gift_card_id]
+            ).map_err(|e| e.to_string())?;
+            insert_entry(conn, &gift_card_id, Some(transaction_id), "refund", -amount, None, Some(note), user_id)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Sell a gift card. Cash paid for it goes into the shift's drawer; the card is
+// a liability, not a sale, so it does not appear in sales figures.
+#[tauri::command]
+pub fn sell_gift_card(sale: GiftCardSale, db: State<Database>) -> Result<GiftCard, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    let methods = crate::payments::load_payment_methods(&tx)?;
+    let method = methods.iter()
+        .find(|m| m.code == sale.payment_method && m.is_active)
+        .ok_or_else(|| format!("Unknown or inactive payment method: {}", sale.payment_method))?;
+    if [GIFT_CARD_METHOD, STORE_CREDIT_METHOD, crate::loyalty::POINTS_METHOD, crate::receivables::ON_ACCOUNT_METHOD].contains(&method.code.as_str()) {
+        return Err(format!("Gift cards cannot be paid with {}", method.display_name));
+    }
+
+    let drawer_shift = sale.shift_id.as_deref().filter(|_| method.is_drawer_cash);
+    if let Some(shift_id) = drawer_shift {
+        let status: String = tx.query_row(
+            "SELECT status FROM cash_shifts WHERE id = ?1",
+            params![shift_id],
+            |row| row.get(0)
+        ).map_err(|_| "Shift not found".to_string())?;
+        if status != "open" {
+            return Err("Shift is not open".to_string());
+        }
+    }
+
+    let card = issue_card(
+        &tx,
+        sale.code.as_deref(),
+        GIFT_CARD_METHOD,
+        sale.amount,
+        sale.customer_id.as_deref(),
+        sale.expires_at.as_deref(),
+        None,
+        Some(&method.code),
+        Some(&sale.user_id),
+    )?;
+
+    if let Some(shift_id) = drawer_shift {
+        tx.execute(
+            "INSERT INTO cash_movements (id, shift_id, movement_type, amount, reason, timestamp, user_id) VALUES (?1, ?2, 'cash_in', ?3, ?4, ?5, ?6)",
+            params![Uuid::new_v4().to_string(), shift_id, card.balance, format!("Gift card {}", card.code), Utc::now().to_rfc3339(), sale.user_id]
+        ).map_err(|e| e.to_string())?;
+
+        tx.execute(
+            "UPDATE cash_shifts SET expected_cash = expected_cash + ?1 WHERE id = ?2",
+            params![card.balance, shift_id]
+        ).map_err(|e| e.to_string())?;
+    }
+
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(card)
+}
+
+// Card details for a balance check
+#[tauri::command]
+pub fn get_gift_card(code: String, db: State<Database>) -> Result<Option<GiftCard>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    find_card(&conn, &code)
+}
+
+// Full history of a card, oldest first
+#[tauri::command]
+pub fn get_gift_card_history(code: String, db: State<Database>) -> Result<Vec<GiftCardEntry>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let card = find_card(&conn, &code)?.ok_or_else(|| "Card not found".to_string())?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, gift_card_id, transaction_id, entry_type, amount, balance_after, payment_method, note, user_id, created_at
+         FROM gift_card_ledger
+         WHERE gift_card_id = ?1
+         ORDER BY created_at, rowid"
+    ).map_err(|e| e.to_string())?;
+
+    let entry_iter = stmt.query_map(params![card.id], |row| {
+        Ok(GiftCardEntry {
+            id: row.get(0)?,
+            gift_card_id: row.get(1)?,
+            transaction_id: row.get(2)?,
+            entry_type: row.get(3)?,
+            amount: row.get(4)?,
+            balance_after: row.get(5)?,
+            payment_method: row.get(6)?,
+            note: row.get(7)?,
+            user_id: row.get(8)?,
+            created_at: row.get(9)?,
+        })
+    }).map_err(|e| e.to_string())?;
+
+    let mut entries = Vec::new();
+    for entry in entry_iter {
+        entries.push(entry.map_err(|e| e.to_string())?);
+    }
+
+    Ok(entries)
+}
+
+// Block a lost or stolen card; the remaining balance stays on the ledger
+#[tauri::command]
+pub fn deactivate_gift_card(code: String, reason: String, approval: LoginData, db: State<Database>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let approved_by = crate::verify_manager_approval(&conn, &approval)?;
+    let card = find_card(&conn, &code)?.ok_or_else(|| "Card not found".to_string())?;
+
+    conn.execute("UPDATE gift_cards SET is_active = 0 WHERE id = ?1", params![card.id]).map_err(|e| e.to_string())?;
+    insert_entry(&conn, &card.id, None, "adjust", 0.0, None, Some(reason.trim()), Some(&approved_by))?;
+
+    Ok(())
+}
@@ -0,0 +1,239 @@
+// Inventory movement ledger.
+//
+// Every stock change is a row in `inventory_movements` with a signed quantity,
+// the stock after the change, the document that caused it, the user and a
+// reason. `products.stock` is a cache of the ledger sum: it is only changed
+// through record_movement, and verify_stock reports (and can repair) products
+// where the two disagree.
+
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::Database;
+
+pub const MOVEMENT_TYPES: [&str; 7] = ["sale", "return", "receipt", "adjustment", "transfer", "waste", "count"];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InventoryMovement {
+    pub id: String,
+    pub product_id: String,
+    pub movement_type: String,
+    pub quantity: i32,
+    pub stock_after: i32,
+    pub reference_type: Option<String>,
+    pub reference_id: Option<String>,
+    pub user_id: Option<String>,
+    pub reason: Option<String>,
+    pub created_at: String,
+}
+
+// A manual stock movement entered from the back office. Sale and count
+// movements are only written by sales and stocktakes.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StockMovementInput {
+    pub product_id: String,
+    pub movement_type: String,
+    pub quantity: i32,
+    pub reference: Option<String>,
+    pub reason: Option<String>,
+    pub user_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StockCard {
+    pub product_id: String,
+    pub product_name: String,
+    pub opening_stock: i32,
+    pub movements: Vec<InventoryMovement>,
+    pub closing_stock: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StockDiscrepancy {
+    pub product_id: String,
+    pub product_name: String,
+    pub cached_stock: i32,
+    pub ledger_stock: i32,
+}
+
+// Post a stock change and update the cached stock. Returns the stock after it.
+#[allow(clippy::too_many_arguments)]
+pub fn record_movement(
+    conn: &Connection,
+    product_id: &str,
+    movement_type: &str,
+    quantity: i32,
+    reference_type: Option<&str>,
+    reference_id: Option<&str>,
+    user_id: Option<&str>,
+    reason: Option<&str>,
+) -> Result<i32, String> {
+    if !MOVEMENT_TYPES.contains(&movement_type) {
+        return Err(format!("Unknown movement type: {}", movement_type));
+    }
+
+    let updated = conn.execute(
+        "UPDATE products SET stock = stock + ?1 WHERE id = ?2",
+        params![quantity, product_id]
+    ).map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err(format!("Product {} not found", product_id));
+    }
+
+    let stock_after: i32 = conn.query_row(
+        "SELECT stock FROM products WHERE id = ?1",
+        params![product_id],
+        |row| row.get(0)
+    ).map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "INSERT INTO inventory_movements (id, product_id, movement_type, quantity, stock_after, reference_type, reference_id, user_id, reason, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![Uuid::new_v4().to_string(), product_id, movement_type, quantity, stock_after, reference_type, reference_id, user_id, reason, Utc::now().to_rfc3339()]
+    ).map_err(|e| e.to_string())?;
+
+    Ok(stock_after)
+}
+
+// Give products that predate the ledger an opening entry for their current stock
+pub fn post_opening_balances(conn: &Connection) -> rusqlite::Result<()> {
+    let now = Utc::now().to_rfc3339();
+    let mut stmt = conn.prepare(
+        "SELECT id, stock FROM products p
+         WHERE stock != 0 AND NOT EXISTS (SELECT 1 FROM inventory_movements m WHERE m.product_id = p.id)"
+    )?;
+    let products = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i32>(1)?)))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for (product_id, stock) in products {
+        conn.execute(
+            "INSERT INTO inventory_movements (id, product_id, movement_type, quantity, stock_after, reason, created_at)
+             VALUES (?1, ?2, 'adjustment', ?3, ?3, 'Opening balance', ?4)",
+            params![Uuid::new_v4().to_string(), product_id, stock, now]
+        )?;
+    }
+
+    Ok(())
+}
+
+fn map_movement(row: &rusqlite::Row) -> rusqlite::Result<InventoryMovement> {
+    Ok(InventoryMovement {
+        id: row.get(0)?,
+        product_id: row.get(1)?,
+        movement_type: row.get(2)?,
+        quantity: row.get(3)?,
+        stock_after: row.get(4)?,
+        reference_type: row.get(5)?,
+        reference_id: row.get(6)?,
+        user_id: row.get(7)?,
+        reason: row.get(8)?,
+        created_at: row.get(9)?,
+    })
+}
+
+#[tauri::command]
+pub fn record_stock_movement(movement: StockMovementInput, db: State<Database>) -> Result<i32, String> {
+    if movement.movement_type == "sale" || movement.movement_type == "count" {
+        return Err(format!("{} movements cannot be entered manually", movement.movement_type));
+    }
+    if movement.quantity == 0 {
+        return Err("Quantity must not be zero".to_string());
+    }
+    // Receipts and returns add stock, waste takes it away; adjustments and
+    // transfers carry their own sign
+    let quantity = match movement.movement_type.as_str() {
+        "receipt" | "return" => movement.quantity.abs(),
+        "waste" => -movement.quantity.abs(),
+        _ => movement.quantity,
+    };
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    record_movement(
+        &conn,
+        &movement.product_id,
+        &movement.movement_type,
+        quantity,
+        movement.reference.as_deref().map(|_| "manual"),
+        movement.reference.as_deref(),
+        Some(&movement.user_id),
+        movement.reason.as_deref(),
+    )
+}
+
+// Movements of one product between two dates (`end_date` exclusive), with the
+// stock before the first and after the last
+#[tauri::command]
+pub fn get_stock_card(product_id: String, start_date: Option<String>, end_date: Option<String>, db: State<Database>) -> Result<StockCard, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let product_name: String = conn.query_row(
+        "SELECT name FROM products WHERE id = ?1",
+        params![product_id],
+        |row| row.get(0)
+    ).map_err(|_| format!("Product {} not found", product_id))?;
+
+    let start_date = start_date.unwrap_or_default();
+    let opening_stock: i32 = conn.query_row(
+        "SELECT COALESCE(SUM(quantity), 0) FROM inventory_movements WHERE product_id = ?1 AND created_at < ?2",
+        params![product_id, start_date],
+        |row| row.get(0)
+    ).map_err(|e| e.to_string())?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, product_id, movement_type, quantity, stock_after, reference_type, reference_id, user_id, reason, created_at
+         FROM inventory_movements
+         WHERE product_id = ?1 AND created_at >= ?2 AND (?3 IS NULL OR created_at < ?3)
+         ORDER BY created_at, rowid"
+    ).map_err(|e| e.to_string())?;
+
+    let movement_iter = stmt.query_map(params![product_id, start_date, end_date], map_movement).map_err(|e| e.to_string())?;
+
+    let mut movements = Vec::new();
+    for movement in movement_iter {
+        movements.push(movement.map_err(|e| e.to_string())?);
+    }
+
+    let closing_stock = opening_stock + movements.iter().map(|m| m.quantity).sum::<i32>();
+
+    Ok(StockCard { product_id, product_name, opening_stock, movements, closing_stock })
+}
+
+// Products whose cached stock differs from their ledger; with `repair` the
+// cache is reset to the ledger. Products added without any movement get an
+// opening balance first rather than being reported.
+#[tauri::command]
+pub fn verify_stock(repair: Option<bool>, db: State<Database>) -> Result<Vec<StockDiscrepancy>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    post_opening_balances(&conn).map_err(|e| e.to_string())?;
+
+    let discrepancies = conn.prepare(
+        "SELECT p.id, p.name, p.stock, COALESCE((SELECT SUM(m.quantity) FROM inventory_movements m WHERE m.product_id = p.id), 0) AS ledger
+         FROM products p
+         WHERE p.stock != ledger
+         ORDER BY p.name"
+    ).map_err(|e| e.to_string())?
+    .query_map([], |row| {
+        Ok(StockDiscrepancy {
+            product_id: row.get(0)?,
+            product_name: row.get(1)?,
+            cached_stock: row.get(2)?,
+            ledger_stock: row.get(3)?,
+        })
+    }).map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+
+    if repair.unwrap_or(false) {
+        for discrepancy in &discrepancies {
+            conn.execute(
+                "UPDATE products SET stock = ?1 WHERE id = ?2",
+                params![discrepancy.ledger_stock, discrepancy.product_id]
+            ).map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(discrepancies)
+}
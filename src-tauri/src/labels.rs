@@ -0,0 +1,530 @@
+// Shelf labels and price tags.
+//
+// A label shows a product's name, shelf price, barcode and unit price. Labels
+// go to a thermal label printer (a `printer_settings` row of type "label") as
+// raw TSPL or ZPL commands, or are laid out on A4 label sheets as a PDF.
+// Products are picked by id or by `products.price_changed_at`, which is
+// stamped whenever a product's shelf price changes.
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::printing::format_amount;
+use crate::{Database, PrinterSettings};
+
+// Net content units; the unit price is per kg, l or piece
+const CONTENT_UNITS: [&str; 5] = ["g", "kg", "ml", "l", "pcs"];
+
+// A4 sheet of 3 x 8 labels, 70 x 37 mm each, centred on the page
+const SHEET_WIDTH_MM: f64 = 210.0;
+const SHEET_HEIGHT_MM: f64 = 297.0;
+const SHEET_COLUMNS: usize = 3;
+const SHEET_ROWS: usize = 8;
+const SHEET_LABEL_WIDTH_MM: f64 = 70.0;
+const SHEET_LABEL_HEIGHT_MM: f64 = 37.0;
+
+// EAN/UPC digit patterns (1 = bar); R codes are the L codes inverted and G
+// codes the R codes reversed
+const EAN_L_CODES: [&str; 10] = [
+    "0001101", "0011001", "0010011", "0111101", "0100011",
+    "0110001", "0101111", "0111011", "0110111", "0001011",
+];
+// L/G pattern of the left half of an EAN-13, chosen by its first digit
+const EAN_PARITY: [&str; 10] = [
+    "LLLLLL", "LLGLGG", "LLGGLG", "LLGGGL", "LGLLGG",
+    "LGGLLG", "LGGGLL", "LGLGLG", "LGLGGL", "LGGLGL",
+];
+
+// Code128 bar/space widths by symbol value; 104 is Start B
+const CODE128_PATTERNS: [&str; 105] = [
+    "212222", "222122", "222221", "121223", "121322", "131222", "122213", "122312", "132212", "221213",
+    "221312", "231212", "112232", "122132", "122231", "113222", "123122", "123221", "223211", "221132",
+    "221231", "213212", "223112", "312131", "311222", "321122", "321221", "312212", "322112", "322211",
+    "212123", "212321", "232121", "111323", "131123", "131321", "112313", "132113", "132311", "211313",
+    "231113", "231311", "112133", "112331", "132131", "113123", "113321", "133121", "313121", "211331",
+    "231131", "213113", "213311", "213131", "311123", "311321", "331121", "312113", "312311", "332111",
+    "314111", "221411", "431111", "111224", "111422", "121124", "121421", "141122", "141221", "112214",
+    "112412", "122114", "122411", "142112", "142211", "241211", "221114", "413111", "241112", "134111",
+    "111242", "121142", "121241", "114212", "124112", "124211", "411212", "421112", "421211", "212141",
+    "214121", "412121", "111143", "111341", "131141", "114113", "114311", "411113", "411311", "113141",
+    "114131", "311141", "411131", "211412", "211214",
+];
+const CODE128_START_B: usize = 104;
+const CODE128_STOP: &str = "2331112";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LabelSelection {
+    #[serde(default)]
+    pub product_ids: Vec<String>,
+    // Also label every product whose price changed at or after this date or
+    // RFC 3339 timestamp
+    pub changed_since: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShelfLabel {
+    pub product_id: String,
+    pub name: String,
+    pub price: f64,
+    pub barcode: String,
+    // Symbology the barcode is printed in; None when it cannot be encoded
+    pub barcode_format: Option<String>,
+    // e.g. "12.50 / kg"
+    pub unit_price: Option<String>,
+}
+
+// Thermal label size from the printer's config
+struct LabelStock {
+    width_mm: f64,
+    height_mm: f64,
+    gap_mm: f64,
+    dots_per_mm: f64,
+}
+
+impl LabelStock {
+    fn from_config(config: &serde_json::Value) -> Self {
+        LabelStock {
+            width_mm: config["label_width"].as_f64().unwrap_or(50.0),
+            height_mm: config["label_height"].as_f64().unwrap_or(30.0),
+            gap_mm: config["label_gap"].as_f64().unwrap_or(2.0),
+            dots_per_mm: config["dpi"].as_f64().unwrap_or(203.0) / 25.4,
+        }
+    }
+
+    fn dots(&self, mm: f64) -> i64 {
+        (mm * self.dots_per_mm).round() as i64
+    }
+}
+
+// Unit price: per kg for weighed products (their price already is), otherwise
+// worked out from the product's net content
+fn unit_price(price: f64, sold_by_weight: bool, content_quantity: Option<f64>, content_unit: Option<&str>) -> Option<String> {
+    if sold_by_weight {
+        return Some(format!("{} / kg", format_amount(price)));
+    }
+
+    let quantity = content_quantity.filter(|q| *q > 0.0)?;
+    let (per_unit, unit) = match content_unit? {
+        "g" => (price / quantity * 1000.0, "kg"),
+        "kg" => (price / quantity, "kg"),
+        "ml" => (price / quantity * 1000.0, "l"),
+        "l" => (price / quantity, "l"),
+        "pcs" => (price / quantity, "pc"),
+        _ => return None,
+    };
+    Some(format!("{} / {}", format_amount(per_unit), unit))
+}
+
+// Codes saved before barcodes were validated may fail their check digit;
+// printed as Code128 they still scan back to the same product
+fn label_barcode_format(barcode: &str) -> Option<String> {
+    match crate::barcodes::barcode_format(barcode) {
+        Ok(format) => Some(format.to_string()),
+        Err(_) if !barcode.is_empty() && barcode.len() <= 48 && barcode.chars().all(|c| c.is_ascii_graphic() || c == ' ') => Some("Code128".to_string()),
+        Err(_) => None,
+    }
+}
+
+const LABEL_COLUMNS: &str = "id, name, price, barcode, sold_by_weight, content_quantity, content_unit";
+
+fn map_label(row: &rusqlite::Row) -> rusqlite::Result<ShelfLabel> {
+    let price: f64 = row.get(2)?;
+    let barcode: String = row.get(3)?;
+    let content_unit: Option<String> = row.get(6)?;
+    Ok(ShelfLabel {
+        product_id: row.get(0)?,
+        name: row.get(1)?,
+        unit_price: unit_price(price, row.get(4)?, row.get(5)?, content_unit.as_deref()),
+        price,
+        barcode_format: label_barcode_format(&barcode),
+        barcode,
+    })
+}
+
+// Selected products in the order given, followed by products with a price
+// change since the date by category and name; each product appears once
+pub fn load_labels(conn: &Connection, selection: &LabelSelection) -> Result<Vec<ShelfLabel>, String> {
+    if selection.product_ids.is_empty() && selection.changed_since.is_none() {
+        return Err("Select products or a price change date to print labels for".to_string());
+    }
+
+    let mut labels: Vec<ShelfLabel> = Vec::new();
+    for product_id in &selection.product_ids {
+        if labels.iter().any(|l| &l.product_id == product_id) {
+            continue;
+        }
+        let label = conn.query_row(
+            &format!("SELECT {} FROM products WHERE id = ?1", LABEL_COLUMNS),
+            params![product_id],
+            map_label
+        ).map_err(|_| format!("Product {} not found", product_id))?;
+        labels.push(label);
+    }
+
+    if let Some(changed_since) = &selection.changed_since {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM products WHERE price_changed_at >= ?1 ORDER BY category, name",
+            LABEL_COLUMNS
+        )).map_err(|e| e.to_string())?;
+
+        let label_iter = stmt.query_map(params![changed_since], map_label).map_err(|e| e.to_string())?;
+        for label in label_iter {
+            let label = label.map_err(|e| e.to_string())?;
+            if !labels.iter().any(|l| l.product_id == label.product_id) {
+                labels.push(label);
+            }
+        }
+    }
+
+    Ok(labels)
+}
+
+// Printable ASCII for a printer command string; other characters and the
+// language's delimiters become '?'
+fn command_text(text: &str, reserved: &[char], max_chars: usize) -> String {
+    text.chars()
+        .take(max_chars)
+        .map(|c| if (c.is_ascii_graphic() || c == ' ') && !reserved.contains(&c) { c } else { '?' })
+        .collect()
+}
+
+// TSPL string contents, with double quotes written as \["]
+fn tspl_text(text: &str, max_chars: usize) -> String {
+    command_text(text, &[], max_chars).replace('"', "\\[\"]")
+}
+
+// Bars and spaces of a barcode, one entry per module (true = bar)
+fn barcode_modules(format: &str, barcode: &str) -> Vec<bool> {
+    let mut modules = Vec::new();
+    let mut push = |pattern: &str| modules.extend(pattern.chars().map(|c| c == '1'));
+
+    match format {
+        "EAN-13" | "UPC-A" | "EAN-8" => {
+            // UPC-A is an EAN-13 with a leading zero
+            let code = if format == "UPC-A" { format!("0{}", barcode) } else { barcode.to_string() };
+            let digits: Vec<usize> = code.chars().filter_map(|c| c.to_digit(10)).map(|d| d as usize).collect();
+            let (parity, left, right) = if digits.len() == 13 {
+                (EAN_PARITY[digits[0]], &digits[1..7], &digits[7..])
+            } else {
+                ("LLLL", &digits[..4], &digits[4..])
+            };
+            let r_code = |d: usize| -> String { EAN_L_CODES[d].chars().map(|c| if c == '1' { '0' } else { '1' }).collect() };
+
+            push("101");
+            for (digit, set) in left.iter().zip(parity.chars()) {
+                if set == 'G' {
+                    push(&r_code(*digit).chars().rev().collect::<String>());
+                } else {
+                    push(EAN_L_CODES[*digit]);
+                }
+            }
+            push("01010");
+            for digit in right {
+                push(&r_code(*digit));
+            }
+            push("101");
+        }
+        _ => {
+            // Code128 subset B covers printable ASCII
+            let values: Vec<usize> = barcode.bytes().map(|b| (b - 32) as usize).collect();
+            let checksum = values.iter().enumerate().fold(CODE128_START_B, |sum, (i, v)| sum + (i + 1) * v) % 103;
+            let symbols = std::iter::once(CODE128_START_B).chain(values).chain(std::iter::once(checksum));
+
+            for pattern in symbols.map(|v| CODE128_PATTERNS[v]).chain(std::iter::once(CODE128_STOP)) {
+                for (i, width) in pattern.chars().enumerate() {
+                    let bar = if i % 2 == 0 { "1" } else { "0" };
+                    push(&bar.repeat(width.to_digit(10).unwrap_or(1) as usize));
+                }
+            }
+        }
+    }
+
+    modules
+}
+
+// Raw TSPL (TSC and compatible printers), one PRINT per label
+fn encode_tspl(labels: &[ShelfLabel], stock: &LabelStock, copies: u32) -> Vec<u8> {
+    let mut commands = vec![
+        format!("SIZE {} mm,{} mm", stock.width_mm, stock.height_mm),
+        format!("GAP {} mm,0 mm", stock.gap_mm),
+        "DIRECTION 1".to_string(),
+    ];
+    let x = stock.dots(2.0);
+    let usable = stock.dots(stock.width_mm - 4.0);
+    // Font 3 is 16 x 24 dots per character
+    let name_chars = (usable / 16).max(1) as usize;
+
+    for label in labels {
+        commands.push("CLS".to_string());
+        commands.push(format!("TEXT {},{},\"3\",0,1,1,\"{}\"", x, stock.dots(1.5), tspl_text(&label.name, name_chars)));
+        commands.push(format!("TEXT {},{},\"4\",0,2,2,\"{}\"", x, stock.dots(5.0), format_amount(label.price)));
+        if let Some(unit_price) = &label.unit_price {
+            commands.push(format!("TEXT {},{},\"2\",0,1,1,\"{}\"", x, stock.dots(14.0), tspl_text(unit_price, name_chars)));
+        }
+        if let Some(format) = &label.barcode_format {
+            let symbology = match format.as_str() {
+                "EAN-13" => "EAN13",
+                "EAN-8" => "EAN8",
+                "UPC-A" => "UPCA",
+                _ => "128",
+            };
+            let narrow = if barcode_modules(format, &label.barcode).len() as i64 * 2 <= usable { 2 } else { 1 };
+            commands.push(format!(
+                "BARCODE {},{},\"{}\",{},1,0,{},{},\"{}\"",
+                x, stock.dots(17.5), symbology, stock.dots((stock.height_mm - 22.0).max(5.0)), narrow, narrow, tspl_text(&label.barcode, label.barcode.len())
+            ));
+        }
+        commands.push(format!("PRINT 1,{}", copies));
+    }
+
+    commands.iter().map(|c| format!("{}\r\n", c)).collect::<String>().into_bytes()
+}
+
+// Raw ZPL II (Zebra and compatible printers), one format per label
+fn encode_zpl(labels: &[ShelfLabel], stock: &LabelStock, copies: u32) -> Vec<u8> {
+    let mut zpl = String::new();
+    let x = stock.dots(2.0);
+    let usable = stock.dots(stock.width_mm - 4.0);
+    let name_height = stock.dots(3.0);
+    // ^A0 characters are roughly 0.6 of their height wide
+    let name_chars = (usable as f64 / (name_height as f64 * 0.6)).max(1.0) as usize;
+
+    for label in labels {
+        zpl.push_str(&format!("^XA^PW{}^LL{}", stock.dots(stock.width_mm), stock.dots(stock.height_mm)));
+        zpl.push_str(&format!(
+            "^FO{},{}^A0N,{},{}^FD{}^FS",
+            x, stock.dots(1.5), name_height, name_height, command_text(&label.name, &['^', '~'], name_chars)
+        ));
+        zpl.push_str(&format!("^FO{},{}^A0N,{},{}^FD{}^FS", x, stock.dots(5.0), stock.dots(8.0), stock.dots(8.0), format_amount(label.price)));
+        if let Some(unit_price) = &label.unit_price {
+            zpl.push_str(&format!(
+                "^FO{},{}^A0N,{},{}^FD{}^FS",
+                x, stock.dots(14.0), stock.dots(2.5), stock.dots(2.5), command_text(unit_price, &['^', '~'], name_chars)
+            ));
+        }
+        if let Some(format) = &label.barcode_format {
+            let height = stock.dots((stock.height_mm - 22.0).max(5.0));
+            // EAN and UPC fields leave the check digit to the printer
+            let symbol = match format.as_str() {
+                "EAN-13" => format!("^BEN,{},Y,N^FD{}", height, &label.barcode[..12]),
+                "EAN-8" => format!("^B8N,{},Y,N^FD{}", height, &label.barcode[..7]),
+                "UPC-A" => format!("^BUN,{},Y,N,Y^FD{}", height, &label.barcode[..11]),
+                _ => format!("^BCN,{},Y,N,N^FD{}", height, command_text(&label.barcode, &['^', '~'], label.barcode.len())),
+            };
+            let module = if barcode_modules(format, &label.barcode).len() as i64 * 2 <= usable { 2 } else { 1 };
+            zpl.push_str(&format!("^BY{}^FO{},{}{}^FS", module, x, stock.dots(17.5), symbol));
+        }
+        zpl.push_str(&format!("^PQ{}^XZ\n", copies));
+    }
+
+    zpl.into_bytes()
+}
+
+fn mm(value: f64) -> f64 {
+    value * 72.0 / 25.4
+}
+
+// PDF string literal in WinAnsi encoding; characters outside Latin-1 become '?'
+fn pdf_string(text: &str) -> String {
+    let mut out = String::from("(");
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            ' '..='~' => out.push(c),
+            '\u{a0}'..='\u{ff}' => out.push_str(&format!("\\{:03o}", c as u32)),
+            _ => out.push('?'),
+        }
+    }
+    out.push(')');
+    out
+}
+
+// Drawing operators for one sheet label whose top-left corner is at (left, top) in points
+fn pdf_label(label: &ShelfLabel, left: f64, top: f64) -> String {
+    let padding = mm(3.0);
+    let width = mm(SHEET_LABEL_WIDTH_MM) - 2.0 * padding;
+    let x = left + padding;
+    let mut ops = String::new();
+    let mut text = |font: &str, size: f64, y: f64, value: &str| {
+        // Helvetica averages about half an em per character
+        let max_chars = (width / (size * 0.5)) as usize;
+        let value: String = value.chars().take(max_chars).collect();
+        ops.push_str(&format!("BT /{} {} Tf {:.2} {:.2} Td {} Tj ET\n", font, size, x, y, pdf_string(&value)));
+    };
+
+    text("F1", 9.0, top - padding - 9.0, &label.name);
+    text("F2", 20.0, top - mm(15.0), &format_amount(label.price));
+    if let Some(unit_price) = &label.unit_price {
+        text("F1", 7.0, top - mm(19.0), unit_price);
+    }
+
+    if let Some(format) = &label.barcode_format {
+        let modules = barcode_modules(format, &label.barcode);
+        // Nominal 0.33 mm module, narrower when the code would not fit
+        let module = mm(0.33).min(width / modules.len() as f64);
+        let bottom = top - mm(31.0);
+        let mut bars = String::new();
+        let mut start: Option<usize> = None;
+        for (i, bar) in modules.iter().chain(std::iter::once(&false)).enumerate() {
+            match (*bar, start) {
+                (true, None) => start = Some(i),
+                (false, Some(first)) => {
+                    bars.push_str(&format!("{:.2} {:.2} {:.2} {:.2} re\n", x + first as f64 * module, bottom, (i - first) as f64 * module, mm(10.0)));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        ops.push_str(&bars);
+        ops.push_str("f\n");
+        ops.push_str(&format!("BT /F1 7 Tf {:.2} {:.2} Td {} Tj ET\n", x, bottom - 8.0, pdf_string(&label.barcode)));
+    }
+
+    ops
+}
+
+// A4 label sheets as a PDF; labels fill each sheet row by row
+fn render_pdf(labels: &[ShelfLabel], copies: u32) -> Vec<u8> {
+    let per_page = SHEET_COLUMNS * SHEET_ROWS;
+    let left_margin = (SHEET_WIDTH_MM - SHEET_COLUMNS as f64 * SHEET_LABEL_WIDTH_MM) / 2.0;
+    let top_margin = (SHEET_HEIGHT_MM - SHEET_ROWS as f64 * SHEET_LABEL_HEIGHT_MM) / 2.0;
+
+    let sheet_labels: Vec<&ShelfLabel> = labels.iter().flat_map(|l| std::iter::repeat_n(l, copies as usize)).collect();
+    let pages: Vec<String> = sheet_labels.chunks(per_page).map(|page| {
+        page.iter().enumerate().map(|(i, label)| {
+            let left = mm(left_margin + (i % SHEET_COLUMNS) as f64 * SHEET_LABEL_WIDTH_MM);
+            let top = mm(SHEET_HEIGHT_MM - top_margin - (i / SHEET_COLUMNS) as f64 * SHEET_LABEL_HEIGHT_MM);
+            pdf_label(label, left, top)
+        }).collect()
+    }).collect();
+
+    // Objects 1-4 are the catalog, page tree and fonts; each page then takes
+    // a page object and its content stream
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 5 + i * 2).collect();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids.iter().map(|id| format!("{} 0 R", id)).collect::<Vec<_>>().join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_string(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>".to_string(),
+    ];
+    for (content, id) in pages.iter().zip(&page_ids) {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+            mm(SHEET_WIDTH_MM), mm(SHEET_HEIGHT_MM), id + 1
+        ));
+        objects.push(format!("<< /Length {} >>\nstream\n{}endstream", content.len(), content));
+    }
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", i + 1, object));
+    }
+    let xref = pdf.len();
+    pdf.push_str(&format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1));
+    for offset in offsets {
+        pdf.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    pdf.push_str(&format!("trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", objects.len() + 1, xref));
+
+    pdf.into_bytes()
+}
+
+fn label_copies(copies: Option<u32>) -> Result<u32, String> {
+    match copies.unwrap_or(1) {
+        0 => Err("Copies must be at least 1".to_string()),
+        copies => Ok(copies),
+    }
+}
+
+// Net content of a product (e.g. 500 g) for the unit price on its label;
+// None clears it
+#[tauri::command]
+pub fn set_product_content(product_id: String, content_quantity: Option<f64>, content_unit: Option<String>, db: State<Database>) -> Result<(), String> {
+    let (content_quantity, content_unit) = match (content_quantity, content_unit) {
+        (Some(quantity), Some(unit)) => {
+            if !quantity.is_finite() || quantity <= 0.0 {
+                return Err("Content quantity must be greater than zero".to_string());
+            }
+            if !CONTENT_UNITS.contains(&unit.as_str()) {
+                return Err(format!("Content unit must be one of {}", CONTENT_UNITS.join(", ")));
+            }
+            (Some(quantity), Some(unit))
+        }
+        (None, None) => (None, None),
+        _ => return Err("Content quantity and unit go together".to_string()),
+    };
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let updated = conn.execute(
+        "UPDATE products SET content_quantity = ?1, content_unit = ?2 WHERE id = ?3",
+        params![content_quantity, content_unit, product_id]
+    ).map_err(|e| e.to_string())?;
+
+    if updated == 0 {
+        return Err(format!("Product {} not found", product_id));
+    }
+
+    Ok(())
+}
+
+// Preview the labels a selection would print
+#[tauri::command]
+pub fn get_shelf_labels(selection: LabelSelection, db: State<Database>) -> Result<Vec<ShelfLabel>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    load_labels(&conn, &selection)
+}
+
+// Print labels on a thermal label printer. The printer's config sets the
+// command `language` ("tspl" or "zpl") and the label stock: `label_width`,
+// `label_height` and `label_gap` in mm and the print head `dpi`.
+#[tauri::command]
+pub fn print_shelf_labels(selection: LabelSelection, copies: Option<u32>, printer_id: Option<String>, db: State<Database>) -> Result<usize, String> {
+    let copies = label_copies(copies)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let printer: PrinterSettings = crate::load_printer_settings(&conn, printer_id.as_deref(), "label")?;
+    if printer.printer_type != "label" {
+        return Err(format!("{} is not a label printer", printer.name));
+    }
+    let labels = load_labels(&conn, &selection)?;
+    if labels.is_empty() {
+        return Ok(0);
+    }
+
+    let stock = LabelStock::from_config(&printer.config);
+    let commands = match printer.config["language"].as_str().unwrap_or("tspl") {
+        "tspl" => encode_tspl(&labels, &stock, copies),
+        "zpl" => encode_zpl(&labels, &stock, copies),
+        language => return Err(format!("Unsupported label printer language: {}", language)),
+    };
+    crate::printing::print_raw(&printer, &commands)?;
+
+    Ok(labels.len())
+}
+
+// Write labels to `path` as a PDF of A4 sheets (3 x 8 labels of 70 x 37 mm)
+#[tauri::command]
+pub fn export_shelf_labels_pdf(selection: LabelSelection, copies: Option<u32>, path: String, db: State<Database>) -> Result<usize, String> {
+    let copies = label_copies(copies)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let labels = load_labels(&conn, &selection)?;
+    if labels.is_empty() {
+        return Ok(0);
+    }
+
+    std::fs::write(&path, render_pdf(&labels, copies)).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+
+    Ok(labels.len())
+}
@@ -0,0 +1,328 @@
+// Loyalty points.
+//
+// Customers earn whole points on the part of a sale not paid with points:
+// each line's amount after discounts is weighted by its category multiplier,
+// and one point is given per `loyalty_earn_amount` of weighted spend (rounded
+// down). Points are redeemed through the "points" payment method, each point
+// worth `loyalty_point_value`. Earned points expire after
+// `loyalty_expiry_days` (0 keeps them forever); points used or removed are
+// taken from the oldest earned points first.
+//
+// Every change is a row in `loyalty_ledger` and a balance is the sum of its
+// points. Entry types: earn, redeem, expire and adjust (manual corrections and
+// reversals of voided sales).
+
+use chrono::{Duration, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::payments::Payment;
+use crate::pricing::round_money;
+use crate::{Database, LoginData};
+
+// Payment method code used to redeem points
+pub const POINTS_METHOD: &str = "points";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoyaltyEntry {
+    pub id: String,
+    pub customer_id: String,
+    pub transaction_id: Option<String>,
+    pub entry_type: String,
+    pub points: i64,
+    pub reason: Option<String>,
+    pub expires_at: Option<String>,
+    pub user_id: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoyaltyBalance {
+    pub customer_id: String,
+    pub points: i64,
+    // Currency value of the balance when redeemed
+    pub value: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CategoryMultiplier {
+    pub category: String,
+    pub multiplier: f64,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn insert_entry(
+    conn: &Connection,
+    customer_id: &str,
+    transaction_id: Option<&str>,
+    entry_type: &str,
+    points: i64,
+    reason: Option<&str>,
+    expires_at: Option<&str>,
+    user_id: Option<&str>,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO loyalty_ledger (id, customer_id, transaction_id, entry_type, points, reason, expires_at, user_id, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![Uuid::new_v4().to_string(), customer_id, transaction_id, entry_type, points, reason, expires_at, user_id, Utc::now().to_rfc3339()]
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+// Post expire entries for earned points past their expiry that have not been
+// used. Used points are counted against the oldest earned points, so what can
+// expire is the expired earnings minus everything taken out so far.
+pub fn expire_points(conn: &Connection, customer_id: &str) -> Result<(), String> {
+    // Entries of voided sales cancel out and are left out
+    let (expired_earned, taken_out): (i64, i64) = conn.query_row(
+        "SELECT
+             COALESCE(SUM(CASE WHEN l.entry_type = 'earn' AND l.expires_at <= ?2 THEN l.points ELSE 0 END), 0),
+             COALESCE(SUM(CASE WHEN l.points < 0 THEN -l.points ELSE 0 END), 0)
+         FROM loyalty_ledger l
+         LEFT JOIN transactions t ON t.id = l.transaction_id
+         WHERE l.customer_id = ?1 AND t.voided_at IS NULL",
+        params![customer_id, Utc::now().to_rfc3339()],
+        |row| Ok((row.get(0)?, row.get(1)?))
+    ).map_err(|e| e.to_string())?;
+
+    let to_expire = (expired_earned - taken_out).min(balance(conn, customer_id)?);
+    if to_expire > 0 {
+        insert_entry(conn, customer_id, None, "expire", -to_expire, Some("Points expired"), None, None)?;
+    }
+
+    Ok(())
+}
+
+fn balance(conn: &Connection, customer_id: &str) -> Result<i64, String> {
+    conn.query_row(
+        "SELECT COALESCE(SUM(points), 0) FROM loyalty_ledger WHERE customer_id = ?1",
+        params![customer_id],
+        |row| row.get(0)
+    ).map_err(|e| e.to_string())
+}
+
+// Current balance after expiring old points
+pub fn current_balance(conn: &Connection, customer_id: &str) -> Result<i64, String> {
+    expire_points(conn, customer_id)?;
+    balance(conn, customer_id)
+}
+
+// Points value of a redeemed amount; amounts must be whole points
+fn points_for_amount(amount: f64, point_value: f64) -> Result<i64, String> {
+    if point_value <= 0.0 {
+        return Err("Loyalty points cannot be redeemed".to_string());
+    }
+    let points = amount / point_value;
+    if (points - points.round()).abs() > 1e-6 {
+        return Err(format!("Points payments must be a multiple of {:.2}", point_value));
+    }
+    Ok(points.round() as i64)
+}
+
+fn category_multiplier(conn: &Connection, product_id: &str) -> Result<f64, String> {
+    match conn.query_row(
+        "SELECT COALESCE(m.multiplier, 1.0)
+         FROM products p
+         LEFT JOIN loyalty_category_multipliers m ON m.category = p.category
+         WHERE p.id = ?1",
+        params![product_id],
+        |row| row.get(0)
+    ) {
+        Ok(multiplier) => Ok(multiplier),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(1.0),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// Redeem points tenders and award points for a completed sale. `lines` are
+// (product id, amount after all discounts). Returns the points earned.
+#[allow(clippy::too_many_arguments)]
+pub fn record_sale(
+    conn: &Connection,
+    transaction_id: &str,
+    customer_id: Option<&str>,
+    lines: &[(String, f64)],
+    payments: &[Payment],
+    total: f64,
+    user_id: Option<&str>,
+    timestamp: &str,
+) -> Result<i64, String> {
+    let redeemed_amount: f64 = payments.iter().filter(|p| p.method == POINTS_METHOD).map(|p| p.amount).sum();
+
+    let customer_id = match customer_id {
+        Some(customer_id) => customer_id,
+        None if redeemed_amount > 0.0 => return Err("Points can only be redeemed by a customer".to_string()),
+        None => return Ok(0),
+    };
+
+    if redeemed_amount > 0.0 {
+        let point_value = crate::setting_f64(conn, "loyalty_point_value", 1.0)?;
+        let points = points_for_amount(redeemed_amount, point_value)?;
+        let available = current_balance(conn, customer_id)?;
+        if points > available {
+            return Err(format!("Customer has {} points, {} needed", available, points));
+        }
+        insert_entry(conn, customer_id, Some(transaction_id), "redeem", -points, None, None, user_id)?;
+    }
+
+    // Points are only earned on what was not paid with points
+    let earn_amount = crate::setting_f64(conn, "loyalty_earn_amount", 0.0)?;
+    if earn_amount <= 0.0 || total <= 0.0 {
+        return Ok(0);
+    }
+    let paid_share = ((total - redeemed_amount) / total).clamp(0.0, 1.0);
+
+    let mut weighted = 0.0;
+    for (product_id, amount) in lines {
+        weighted += amount * category_multiplier(conn, product_id)?;
+    }
+    let earned = (round_money(weighted * paid_share) / earn_amount).floor() as i64;
+
+    if earned > 0 {
+        let expiry_days = crate::setting_f64(conn, "loyalty_expiry_days", 0.0)?;
+        let expires_at = chrono::DateTime::parse_from_rfc3339(timestamp)
+            .ok()
+            .filter(|_| expiry_days > 0.0)
+            .map(|time| (time + Duration::days(expiry_days as i64)).with_timezone(&Utc).to_rfc3339());
+        insert_entry(conn, customer_id, Some(transaction_id), "earn", earned, None, expires_at.as_deref(), user_id)?;
+    }
+
+    Ok(earned)
+}
+
+// Undo the points a sale earned and return the points it redeemed
+pub fn reverse_sale(conn: &Connection, transaction_id: &str, reason: &str, user_id: Option<&str>) -> Result<(), String> {
+    let mut stmt = conn.prepare(
+        "SELECT customer_id, SUM(points)
+         FROM loyalty_ledger
+         WHERE transaction_id = ?1
+         GROUP BY customer_id"
+    ).map_err(|e| e.to_string())?;
+
+    let totals = stmt.query_map(params![transaction_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    for (customer_id, points) in totals {
+        if points != 0 {
+            insert_entry(conn, &customer_id, Some(transaction_id), "adjust", -points, Some(reason), None, user_id)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_loyalty_balance(customer_id: String, db: State<Database>) -> Result<LoyaltyBalance, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let points = current_balance(&conn, &customer_id)?;
+    let point_value = crate::setting_f64(&conn, "loyalty_point_value", 1.0)?;
+
+    Ok(LoyaltyBalance { customer_id, points, value: round_money(points as f64 * point_value) })
+}
+
+// Ledger entries for a customer, newest first
+#[tauri::command]
+pub fn get_loyalty_ledger(customer_id: String, limit: Option<i64>, db: State<Database>) -> Result<Vec<LoyaltyEntry>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    expire_points(&conn, &customer_id)?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, customer_id, transaction_id, entry_type, points, reason, expires_at, user_id, created_at
+         FROM loyalty_ledger
+         WHERE customer_id = ?1
+         ORDER BY created_at DESC, rowid DESC
+         LIMIT ?2"
+    ).map_err(|e| e.to_string())?;
+
+    let entry_iter = stmt.query_map(params![customer_id, limit.unwrap_or(100).max(1)], |row| {
+        Ok(LoyaltyEntry {
+            id: row.get(0)?,
+            customer_id: row.get(1)?,
+            transaction_id: row.get(2)?,
+            entry_type: row.get(3)?,
+            points: row.get(4)?,
+            reason: row.get(5)?,
+            expires_at: row.get(6)?,
+            user_id: row.get(7)?,
+            created_at: row.get(8)?,
+        })
+    }).map_err(|e| e.to_string())?;
+
+    let mut entries = Vec::new();
+    for entry in entry_iter {
+        entries.push(entry.map_err(|e| e.to_string())?);
+    }
+
+    Ok(entries)
+}
+
+// Manual correction of a customer's points; needs a manager's approval
+#[tauri::command]
+pub fn adjust_loyalty_points(customer_id: String, points: i64, reason: String, approval: LoginData, db: State<Database>) -> Result<LoyaltyBalance, String> {
+    if points == 0 {
+        return Err("Adjustment must change the balance".to_string());
+    }
+    if reason.trim().is_empty() {
+        return Err("A reason is required for point adjustments".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let approved_by = crate::verify_manager_approval(&conn, &approval)?;
+
+    let available = current_balance(&conn, &customer_id)?;
+    if available + points < 0 {
+        return Err(format!("Customer has only {} points", available));
+    }
+
+    insert_entry(&conn, &customer_id, None, "adjust", points, Some(reason.trim()), None, Some(&approved_by))?;
+
+    let point_value = crate::setting_f64(&conn, "loyalty_point_value", 1.0)?;
+    let points = balance(&conn, &customer_id)?;
+    Ok(LoyaltyBalance { customer_id, points, value: round_money(points as f64 * point_value) })
+}
+
+#[tauri::command]
+pub fn get_loyalty_multipliers(db: State<Database>) -> Result<Vec<CategoryMultiplier>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare("SELECT category, multiplier FROM loyalty_category_multipliers ORDER BY category").map_err(|e| e.to_string())?;
+
+    let multiplier_iter = stmt.query_map([], |row| {
+        Ok(CategoryMultiplier {
+            category: row.get(0)?,
+            multiplier: row.get(1)?,
+        })
+    }).map_err(|e| e.to_string())?;
+
+    let mut multipliers = Vec::new();
+    for multiplier in multiplier_iter {
+        multipliers.push(multiplier.map_err(|e| e.to_string())?);
+    }
+
+    Ok(multipliers)
+}
+
+// Set the earning multiplier for a product category; 1.0 removes the override
+#[tauri::command]
+pub fn set_loyalty_multiplier(category: String, multiplier: f64, db: State<Database>) -> Result<(), String> {
+    if multiplier < 0.0 || !multiplier.is_finite() {
+        return Err("Multiplier must be zero or more".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    if multiplier == 1.0 {
+        conn.execute("DELETE FROM loyalty_category_multipliers WHERE category = ?1", params![category]).map_err(|e| e.to_string())?;
+    } else {
+        conn.execute(
+            "INSERT INTO loyalty_category_multipliers (category, multiplier) VALUES (?1, ?2)
+             ON CONFLICT(category) DO UPDATE SET multiplier = excluded.multiplier",
+            params![category, multiplier]
+        ).map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
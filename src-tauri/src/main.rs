@@ -1,13 +1,37 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use tauri::State;
+use tauri::{AppHandle, Emitter, State};
+use std::collections::BTreeMap;
 use std::sync::{Arc, Mutex};
 use rusqlite::{Connection, Result, params};
 use serde::{Deserialize, Serialize};
 use chrono::Utc;
 use uuid::Uuid;
 
+use payments::PaymentInput;
+use pricing::DiscountInput;
+
+mod analytics;
+mod barcodes;
+mod carts;
+mod customers;
+mod giftcards;
+mod inventory;
+mod labels;
+mod loyalty;
+mod payments;
+mod printing;
+mod purchasing;
+mod pricing;
+mod promotions;
+mod receivables;
+mod reorder;
+mod scale;
+mod stocktake;
+mod tax;
+mod variants;
+
 // Database state
 #[derive(Clone)]
 struct Database(Arc<Mutex<Connection>>);
@@ -21,6 +45,39 @@ struct Product {
     price: f64,
     stock: i32,
     category: String,
+    #[serde(default)]
+    tax_class: String,
+    // Weighted-average purchase cost, maintained by goods receipts
+    #[serde(default)]
+    cost_price: f64,
+    #[serde(default)]
+    reorder_point: i32,
+    #[serde(default)]
+    reorder_quantity: i32,
+    // Preferred supplier for reorder suggestions
+    #[serde(default)]
+    supplier_id: Option<String>,
+    // Set for variants: the parent product and this variant's attribute values
+    #[serde(default)]
+    parent_id: Option<String>,
+    #[serde(default)]
+    parent_name: Option<String>,
+    #[serde(default)]
+    variant_attributes: Option<BTreeMap<String, String>>,
+    // Set when looked up by a pack barcode: units of stock per scan
+    #[serde(default)]
+    pack_quantity: Option<i32>,
+    // Priced per kg and sold in decimal quantities; stock is in grams
+    #[serde(default)]
+    sold_by_weight: bool,
+    #[serde(default)]
+    plu: Option<String>,
+    // Set when looked up by a scale label: the kg on the label
+    #[serde(default)]
+    scale_quantity: Option<f64>,
+    // Set for price labels: the amount printed on the label, charged as is
+    #[serde(default)]
+    scale_amount: Option<f64>,
 }
 
 // Transaction data structure
@@ -31,6 +88,37 @@ struct Transaction {
     total: f64,
     timestamp: String,
     payment_method: String,
+    #[serde(default)]
+    user_id: Option<String>,
+    #[serde(default)]
+    shift_id: Option<String>,
+    #[serde(default)]
+    cash_register_id: Option<String>,
+    #[serde(default)]
+    receipt_number: Option<String>,
+    #[serde(default)]
+    reprint_count: i64,
+    #[serde(default)]
+    subtotal: f64,
+    #[serde(default)]
+    discount_total: f64,
+    #[serde(default)]
+    tax_total: f64,
+    #[serde(default)]
+    tax_mode: Option<String>,
+    #[serde(default)]
+    payments: Vec<payments::Payment>,
+    // Cash rounding difference, already included in `total`. It is kept as its
+    // own figure next to subtotal, discounts and tax rather than folded into a
+    // price, so `total` = subtotal - order discount (+ exclusive tax) + rounding
+    // and the tender lines add up to `total`; receipts print it as a separate
+    // line and shift reports total it for the drawer count.
+    #[serde(default)]
+    rounding_amount: f64,
+    #[serde(default)]
+    customer_id: Option<String>,
+    #[serde(default)]
+    voided_at: Option<String>,
 }
 
 // Transaction item structure
@@ -38,9 +126,26 @@ struct Transaction {
 struct TransactionItem {
     product_id: String,
     name: String,
-    quantity: i32,
+    // Whole items, or kg for products sold by weight
+    quantity: f64,
     price: f64,
     subtotal: f64,
+    #[serde(default)]
+    discount_amount: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    line_discount: Option<DiscountInput>,
+    #[serde(default)]
+    tax_class: Option<String>,
+    #[serde(default)]
+    tax_rate: f64,
+    #[serde(default)]
+    tax_amount: f64,
+    // Barcode the item was scanned with; pack barcodes sell several units each
+    #[serde(default)]
+    barcode: Option<String>,
+    // Units of stock per item, set from the barcode when the sale is recorded
+    #[serde(default)]
+    pack_quantity: Option<i32>,
 }
 
 // User data structure
@@ -135,6 +240,8 @@ struct ShiftReport {
     pdf_path: Option<String>,
     generated_at: String,
     generated_by: String,
+    #[serde(default)]
+    report_number: Option<i64>,
 }
 
 // Database operations
@@ -149,22 +256,47 @@ fn init_database() -> Result<Connection> {
             barcode TEXT UNIQUE NOT NULL,
             price REAL NOT NULL,
             stock INTEGER NOT NULL DEFAULT 0,
-            category TEXT NOT NULL
+            category TEXT NOT NULL,
+            tax_class TEXT NOT NULL DEFAULT 'standard'
         )",
         "CREATE TABLE IF NOT EXISTS transactions (
             id TEXT PRIMARY KEY,
             total REAL NOT NULL,
             timestamp TEXT NOT NULL,
-            payment_method TEXT NOT NULL
+            payment_method TEXT NOT NULL,
+            user_id TEXT,
+            shift_id TEXT,
+            cash_register_id TEXT,
+            receipt_number TEXT,
+            reprint_count INTEGER NOT NULL DEFAULT 0,
+            subtotal REAL,
+            discount_total REAL NOT NULL DEFAULT 0,
+            tax_total REAL NOT NULL DEFAULT 0,
+            tax_mode TEXT,
+            rounding_amount REAL NOT NULL DEFAULT 0,
+            customer_id TEXT,
+            voided_at TEXT,
+            voided_by TEXT,
+            void_reason TEXT,
+            FOREIGN KEY (user_id) REFERENCES users (id),
+            FOREIGN KEY (shift_id) REFERENCES cash_shifts (id),
+            FOREIGN KEY (cash_register_id) REFERENCES cash_registers (id)
         )",
+        // Quantities are kg for weighed products. Databases created before that
+        // keep an INTEGER quantity column, which still stores decimals as REAL.
         "CREATE TABLE IF NOT EXISTS transaction_items (
             id TEXT PRIMARY KEY,
             transaction_id TEXT NOT NULL,
             product_id TEXT NOT NULL,
             name TEXT NOT NULL,
-            quantity INTEGER NOT NULL,
+            quantity REAL NOT NULL,
             price REAL NOT NULL,
             subtotal REAL NOT NULL,
+            discount_amount REAL NOT NULL DEFAULT 0,
+            tax_class TEXT,
+            tax_rate REAL NOT NULL DEFAULT 0,
+            taxable_amount REAL NOT NULL DEFAULT 0,
+            tax_amount REAL NOT NULL DEFAULT 0,
             FOREIGN KEY (transaction_id) REFERENCES transactions (id),
             FOREIGN KEY (product_id) REFERENCES products (id)
         )",
@@ -182,7 +314,9 @@ fn init_database() -> Result<Connection> {
             id TEXT PRIMARY KEY,
             name TEXT NOT NULL,
             location TEXT,
-            is_active BOOLEAN DEFAULT 1
+            is_active BOOLEAN DEFAULT 1,
+            code TEXT,
+            receipt_counter INTEGER NOT NULL DEFAULT 0
         )",
         "CREATE TABLE IF NOT EXISTS cash_shifts (
             id TEXT PRIMARY KEY,
@@ -237,8 +371,277 @@ fn init_database() -> Result<Connection> {
             pdf_path TEXT,
             generated_at TEXT NOT NULL,
             generated_by TEXT NOT NULL,
+            report_number INTEGER,
             FOREIGN KEY (shift_id) REFERENCES cash_shifts (id),
             FOREIGN KEY (generated_by) REFERENCES users (id)
+        )",
+        "CREATE TABLE IF NOT EXISTS app_settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        "CREATE TABLE IF NOT EXISTS transaction_discounts (
+            id TEXT PRIMARY KEY,
+            transaction_id TEXT NOT NULL,
+            transaction_item_id TEXT,
+            scope TEXT NOT NULL,
+            discount_type TEXT NOT NULL,
+            value REAL NOT NULL,
+            amount REAL NOT NULL,
+            reason TEXT,
+            approved_by TEXT,
+            created_at TEXT NOT NULL,
+            promotion_id TEXT,
+            FOREIGN KEY (transaction_id) REFERENCES transactions (id),
+            FOREIGN KEY (transaction_item_id) REFERENCES transaction_items (id),
+            FOREIGN KEY (approved_by) REFERENCES users (id),
+            FOREIGN KEY (promotion_id) REFERENCES promotions (id)
+        )",
+        "CREATE TABLE IF NOT EXISTS payments (
+            id TEXT PRIMARY KEY,
+            transaction_id TEXT NOT NULL,
+            method TEXT NOT NULL,
+            amount REAL NOT NULL,
+            tendered REAL NOT NULL,
+            change_given REAL NOT NULL DEFAULT 0,
+            reference TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (transaction_id) REFERENCES transactions (id)
+        )",
+        "CREATE TABLE IF NOT EXISTS customers (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            phone TEXT,
+            email TEXT,
+            tax_id TEXT,
+            notes TEXT,
+            is_member BOOLEAN NOT NULL DEFAULT 0,
+            is_active BOOLEAN NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            credit_limit REAL NOT NULL DEFAULT 0
+        )",
+        "CREATE TABLE IF NOT EXISTS loyalty_ledger (
+            id TEXT PRIMARY KEY,
+            customer_id TEXT NOT NULL,
+            transaction_id TEXT,
+            entry_type TEXT NOT NULL CHECK (entry_type IN ('earn', 'redeem', 'expire', 'adjust')),
+            points INTEGER NOT NULL,
+            reason TEXT,
+            expires_at TEXT,
+            user_id TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (customer_id) REFERENCES customers (id),
+            FOREIGN KEY (transaction_id) REFERENCES transactions (id),
+            FOREIGN KEY (user_id) REFERENCES users (id)
+        )",
+        "CREATE TABLE IF NOT EXISTS loyalty_category_multipliers (
+            category TEXT PRIMARY KEY,
+            multiplier REAL NOT NULL DEFAULT 1
+        )",
+        "CREATE TABLE IF NOT EXISTS gift_cards (
+            id TEXT PRIMARY KEY,
+            code TEXT NOT NULL UNIQUE,
+            card_type TEXT NOT NULL CHECK (card_type IN ('gift_card', 'store_credit')),
+            customer_id TEXT,
+            initial_balance REAL NOT NULL,
+            balance REAL NOT NULL CHECK (balance >= 0),
+            is_active BOOLEAN NOT NULL DEFAULT 1,
+            expires_at TEXT,
+            issued_at TEXT NOT NULL,
+            issued_by TEXT,
+            FOREIGN KEY (customer_id) REFERENCES customers (id),
+            FOREIGN KEY (issued_by) REFERENCES users (id)
+        )",
+        "CREATE TABLE IF NOT EXISTS gift_card_ledger (
+            id TEXT PRIMARY KEY,
+            gift_card_id TEXT NOT NULL,
+            transaction_id TEXT,
+            entry_type TEXT NOT NULL CHECK (entry_type IN ('issue', 'redeem', 'refund', 'adjust')),
+            amount REAL NOT NULL,
+            balance_after REAL NOT NULL,
+            payment_method TEXT,
+            note TEXT,
+            user_id TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (gift_card_id) REFERENCES gift_cards (id),
+            FOREIGN KEY (transaction_id) REFERENCES transactions (id),
+            FOREIGN KEY (user_id) REFERENCES users (id)
+        )",
+        "CREATE TABLE IF NOT EXISTS ar_ledger (
+            id TEXT PRIMARY KEY,
+            customer_id TEXT NOT NULL,
+            transaction_id TEXT,
+            entry_type TEXT NOT NULL CHECK (entry_type IN ('charge', 'payment', 'adjust')),
+            amount REAL NOT NULL,
+            payment_method TEXT,
+            reference TEXT,
+            note TEXT,
+            shift_id TEXT,
+            user_id TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (customer_id) REFERENCES customers (id),
+            FOREIGN KEY (transaction_id) REFERENCES transactions (id),
+            FOREIGN KEY (shift_id) REFERENCES cash_shifts (id),
+            FOREIGN KEY (user_id) REFERENCES users (id)
+        )",
+        "CREATE TABLE IF NOT EXISTS inventory_movements (
+            id TEXT PRIMARY KEY,
+            product_id TEXT NOT NULL,
+            movement_type TEXT NOT NULL CHECK (movement_type IN ('sale', 'return', 'receipt', 'adjustment', 'transfer', 'waste', 'count')),
+            quantity INTEGER NOT NULL,
+            stock_after INTEGER NOT NULL,
+            reference_type TEXT,
+            reference_id TEXT,
+            user_id TEXT,
+            reason TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (product_id) REFERENCES products (id),
+            FOREIGN KEY (user_id) REFERENCES users (id)
+        )",
+        "CREATE TABLE IF NOT EXISTS stocktakes (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            category TEXT,
+            status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'approved', 'cancelled')),
+            created_by TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            approved_by TEXT,
+            approved_at TEXT,
+            FOREIGN KEY (created_by) REFERENCES users (id),
+            FOREIGN KEY (approved_by) REFERENCES users (id)
+        )",
+        "CREATE TABLE IF NOT EXISTS stocktake_lines (
+            stocktake_id TEXT NOT NULL,
+            product_id TEXT NOT NULL,
+            snapshot_stock INTEGER NOT NULL,
+            PRIMARY KEY (stocktake_id, product_id),
+            FOREIGN KEY (stocktake_id) REFERENCES stocktakes (id),
+            FOREIGN KEY (product_id) REFERENCES products (id)
+        )",
+        "CREATE TABLE IF NOT EXISTS stocktake_counts (
+            id TEXT PRIMARY KEY,
+            stocktake_id TEXT NOT NULL,
+            product_id TEXT NOT NULL,
+            quantity INTEGER NOT NULL,
+            user_id TEXT NOT NULL,
+            counted_at TEXT NOT NULL,
+            FOREIGN KEY (stocktake_id, product_id) REFERENCES stocktake_lines (stocktake_id, product_id),
+            FOREIGN KEY (user_id) REFERENCES users (id)
+        )",
+        "CREATE TABLE IF NOT EXISTS parent_products (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            category TEXT NOT NULL,
+            base_price REAL NOT NULL,
+            tax_class TEXT NOT NULL DEFAULT 'standard',
+            attributes TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        "CREATE TABLE IF NOT EXISTS product_barcodes (
+            barcode TEXT PRIMARY KEY,
+            product_id TEXT NOT NULL,
+            pack_quantity INTEGER NOT NULL DEFAULT 1 CHECK (pack_quantity >= 1),
+            pack_price REAL,
+            label TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (product_id) REFERENCES products (id)
+        )",
+        "CREATE TABLE IF NOT EXISTS suppliers (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            contact_name TEXT,
+            phone TEXT,
+            email TEXT,
+            address TEXT,
+            notes TEXT,
+            is_active BOOLEAN NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        "CREATE TABLE IF NOT EXISTS purchase_orders (
+            id TEXT PRIMARY KEY,
+            po_number TEXT NOT NULL UNIQUE,
+            supplier_id TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'partially_received', 'received', 'closed')),
+            expected_date TEXT,
+            notes TEXT,
+            created_by TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            closed_at TEXT,
+            closed_by TEXT,
+            FOREIGN KEY (supplier_id) REFERENCES suppliers (id),
+            FOREIGN KEY (created_by) REFERENCES users (id),
+            FOREIGN KEY (closed_by) REFERENCES users (id)
+        )",
+        "CREATE TABLE IF NOT EXISTS purchase_order_lines (
+            id TEXT PRIMARY KEY,
+            purchase_order_id TEXT NOT NULL,
+            product_id TEXT NOT NULL,
+            quantity_ordered INTEGER NOT NULL,
+            quantity_received INTEGER NOT NULL DEFAULT 0,
+            unit_cost REAL NOT NULL DEFAULT 0,
+            FOREIGN KEY (purchase_order_id) REFERENCES purchase_orders (id),
+            FOREIGN KEY (product_id) REFERENCES products (id)
+        )",
+        "CREATE TABLE IF NOT EXISTS purchase_receipts (
+            id TEXT PRIMARY KEY,
+            purchase_order_id TEXT NOT NULL,
+            purchase_order_line_id TEXT NOT NULL,
+            product_id TEXT NOT NULL,
+            quantity INTEGER NOT NULL,
+            unit_cost REAL NOT NULL,
+            user_id TEXT NOT NULL,
+            received_at TEXT NOT NULL,
+            FOREIGN KEY (purchase_order_id) REFERENCES purchase_orders (id),
+            FOREIGN KEY (purchase_order_line_id) REFERENCES purchase_order_lines (id),
+            FOREIGN KEY (product_id) REFERENCES products (id),
+            FOREIGN KEY (user_id) REFERENCES users (id)
+        )",
+        "CREATE TABLE IF NOT EXISTS parked_carts (
+            id TEXT PRIMARY KEY,
+            cash_register_id TEXT NOT NULL,
+            user_id TEXT NOT NULL,
+            shift_id TEXT,
+            label TEXT,
+            items TEXT NOT NULL,
+            discount TEXT,
+            customer_id TEXT,
+            total REAL NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            expires_at TEXT NOT NULL,
+            FOREIGN KEY (cash_register_id) REFERENCES cash_registers (id),
+            FOREIGN KEY (user_id) REFERENCES users (id),
+            FOREIGN KEY (shift_id) REFERENCES cash_shifts (id)
+        )",
+        "CREATE TABLE IF NOT EXISTS payment_methods (
+            code TEXT PRIMARY KEY,
+            display_name TEXT NOT NULL,
+            is_drawer_cash BOOLEAN NOT NULL DEFAULT 0,
+            opens_drawer BOOLEAN NOT NULL DEFAULT 0,
+            allows_change BOOLEAN NOT NULL DEFAULT 0,
+            requires_reference BOOLEAN NOT NULL DEFAULT 0,
+            is_active BOOLEAN NOT NULL DEFAULT 1,
+            sort_order INTEGER NOT NULL DEFAULT 0
+        )",
+        "CREATE TABLE IF NOT EXISTS tax_classes (
+            code TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            rate REAL NOT NULL,
+            is_active BOOLEAN NOT NULL DEFAULT 1
+        )",
+        "CREATE TABLE IF NOT EXISTS promotions (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            promo_type TEXT NOT NULL,
+            rules TEXT NOT NULL,
+            priority INTEGER NOT NULL DEFAULT 0,
+            stackable BOOLEAN NOT NULL DEFAULT 0,
+            is_active BOOLEAN NOT NULL DEFAULT 1,
+            starts_at TEXT,
+            ends_at TEXT,
+            created_at TEXT NOT NULL
         )"
     ];
     
@@ -246,12 +649,115 @@ fn init_database() -> Result<Connection> {
         conn.execute(sql, [])?;
     }
     
+    // Add columns introduced after the first release to existing databases
+    add_column_if_missing(&conn, "shift_reports", "report_number", "INTEGER")?;
+    add_column_if_missing(&conn, "transactions", "user_id", "TEXT REFERENCES users (id)")?;
+    add_column_if_missing(&conn, "transactions", "shift_id", "TEXT REFERENCES cash_shifts (id)")?;
+    add_column_if_missing(&conn, "transactions", "cash_register_id", "TEXT REFERENCES cash_registers (id)")?;
+    add_column_if_missing(&conn, "transactions", "receipt_number", "TEXT")?;
+    add_column_if_missing(&conn, "transactions", "reprint_count", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(&conn, "transactions", "subtotal", "REAL")?;
+    add_column_if_missing(&conn, "transactions", "discount_total", "REAL NOT NULL DEFAULT 0")?;
+    add_column_if_missing(&conn, "transaction_items", "discount_amount", "REAL NOT NULL DEFAULT 0")?;
+    add_column_if_missing(&conn, "transaction_discounts", "promotion_id", "TEXT REFERENCES promotions (id)")?;
+    add_column_if_missing(&conn, "products", "tax_class", "TEXT NOT NULL DEFAULT 'standard'")?;
+    add_column_if_missing(&conn, "transactions", "tax_total", "REAL NOT NULL DEFAULT 0")?;
+    add_column_if_missing(&conn, "transactions", "tax_mode", "TEXT")?;
+    add_column_if_missing(&conn, "transaction_items", "tax_class", "TEXT")?;
+    add_column_if_missing(&conn, "transaction_items", "tax_rate", "REAL NOT NULL DEFAULT 0")?;
+    add_column_if_missing(&conn, "transaction_items", "taxable_amount", "REAL NOT NULL DEFAULT 0")?;
+    add_column_if_missing(&conn, "transaction_items", "tax_amount", "REAL NOT NULL DEFAULT 0")?;
+    add_column_if_missing(&conn, "transaction_items", "unit_cost", "REAL NOT NULL DEFAULT 0")?;
+    add_column_if_missing(&conn, "transaction_items", "barcode", "TEXT")?;
+    add_column_if_missing(&conn, "transaction_items", "pack_quantity", "INTEGER NOT NULL DEFAULT 1")?;
+    add_column_if_missing(&conn, "transaction_items", "stock_units", "INTEGER")?;
+    add_column_if_missing(&conn, "products", "sold_by_weight", "BOOLEAN NOT NULL DEFAULT 0")?;
+    add_column_if_missing(&conn, "products", "plu", "TEXT")?;
+    add_column_if_missing(&conn, "transactions", "rounding_amount", "REAL NOT NULL DEFAULT 0")?;
+    add_column_if_missing(&conn, "transactions", "customer_id", "TEXT REFERENCES customers (id)")?;
+    add_column_if_missing(&conn, "transactions", "voided_at", "TEXT")?;
+    add_column_if_missing(&conn, "transactions", "voided_by", "TEXT REFERENCES users (id)")?;
+    add_column_if_missing(&conn, "transactions", "void_reason", "TEXT")?;
+    add_column_if_missing(&conn, "customers", "credit_limit", "REAL NOT NULL DEFAULT 0")?;
+    add_column_if_missing(&conn, "products", "cost_price", "REAL NOT NULL DEFAULT 0")?;
+    add_column_if_missing(&conn, "products", "reorder_point", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(&conn, "products", "reorder_quantity", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(&conn, "products", "supplier_id", "TEXT REFERENCES suppliers (id)")?;
+    add_column_if_missing(&conn, "products", "parent_id", "TEXT REFERENCES parent_products (id)")?;
+    add_column_if_missing(&conn, "products", "variant_attributes", "TEXT")?;
+    add_column_if_missing(&conn, "products", "price_override", "REAL")?;
+    add_column_if_missing(&conn, "products", "price_changed_at", "TEXT")?;
+    add_column_if_missing(&conn, "products", "content_quantity", "REAL")?;
+    add_column_if_missing(&conn, "products", "content_unit", "TEXT")?;
+    add_column_if_missing(&conn, "cash_registers", "code", "TEXT")?;
+    add_column_if_missing(&conn, "cash_registers", "receipt_counter", "INTEGER NOT NULL DEFAULT 0")?;
+    
+    // Registers created before receipt numbering get a short code for their receipt prefix
+    conn.execute("UPDATE cash_registers SET code = 'R' || rowid WHERE code IS NULL", [])?;
+    
+    // Items sold before weighed products took whole units of stock per pack
+    conn.execute("UPDATE transaction_items SET stock_units = quantity * pack_quantity WHERE stock_units IS NULL", [])?;
+    
+    // Indexes (created after migrations so the columns exist)
+    let index_statements = vec![
+        // Z-report numbers are sequential and each shift gets at most one Z-report
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_shift_reports_z_number ON shift_reports (report_number) WHERE report_type = 'z'",
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_shift_reports_z_shift ON shift_reports (shift_id) WHERE report_type = 'z'",
+        // Transaction history is read newest first and items are joined by transaction
+        "CREATE INDEX IF NOT EXISTS idx_transactions_timestamp ON transactions (timestamp, id)",
+        "CREATE INDEX IF NOT EXISTS idx_transaction_items_transaction_id ON transaction_items (transaction_id)",
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_transactions_receipt_number ON transactions (receipt_number)",
+        "CREATE INDEX IF NOT EXISTS idx_transaction_discounts_transaction_id ON transaction_discounts (transaction_id)",
+        "CREATE INDEX IF NOT EXISTS idx_payments_transaction_id ON payments (transaction_id)",
+        "CREATE INDEX IF NOT EXISTS idx_transactions_shift_id ON transactions (shift_id)",
+        "CREATE INDEX IF NOT EXISTS idx_parked_carts_register ON parked_carts (cash_register_id, user_id)",
+        "CREATE INDEX IF NOT EXISTS idx_customers_phone ON customers (phone)",
+        "CREATE INDEX IF NOT EXISTS idx_transactions_customer_id ON transactions (customer_id, timestamp)",
+        "CREATE INDEX IF NOT EXISTS idx_loyalty_ledger_customer ON loyalty_ledger (customer_id, created_at)",
+        "CREATE INDEX IF NOT EXISTS idx_loyalty_ledger_transaction ON loyalty_ledger (transaction_id)",
+        "CREATE INDEX IF NOT EXISTS idx_gift_card_ledger_card ON gift_card_ledger (gift_card_id, created_at)",
+        "CREATE INDEX IF NOT EXISTS idx_gift_card_ledger_transaction ON gift_card_ledger (transaction_id)",
+        "CREATE INDEX IF NOT EXISTS idx_ar_ledger_customer ON ar_ledger (customer_id, created_at)",
+        "CREATE INDEX IF NOT EXISTS idx_ar_ledger_transaction ON ar_ledger (transaction_id)",
+        "CREATE INDEX IF NOT EXISTS idx_inventory_movements_product ON inventory_movements (product_id, created_at)",
+        "CREATE INDEX IF NOT EXISTS idx_inventory_movements_reference ON inventory_movements (reference_type, reference_id)",
+        "CREATE INDEX IF NOT EXISTS idx_stocktake_counts_line ON stocktake_counts (stocktake_id, product_id)",
+        "CREATE INDEX IF NOT EXISTS idx_products_parent ON products (parent_id)",
+        "CREATE INDEX IF NOT EXISTS idx_products_price_changed ON products (price_changed_at)",
+        "CREATE INDEX IF NOT EXISTS idx_product_barcodes_product ON product_barcodes (product_id)",
+        "CREATE INDEX IF NOT EXISTS idx_purchase_orders_supplier ON purchase_orders (supplier_id, status)",
+        "CREATE INDEX IF NOT EXISTS idx_purchase_order_lines_order ON purchase_order_lines (purchase_order_id)",
+        "CREATE INDEX IF NOT EXISTS idx_purchase_receipts_order ON purchase_receipts (purchase_order_id)",
+    ];
+    
+    for sql in index_statements {
+        conn.execute(sql, [])?;
+    }
+    
     // Initialize default data
     initialize_default_data(&conn)?;
     
+    // Stock held before the movement ledger existed becomes its opening balance
+    inventory::post_opening_balances(&conn)?;
+    
     Ok(conn)
 }
 
+// Add a column to an existing table unless it is already there
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|name| name.ok())
+        .any(|name| name == column);
+    
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+    
+    Ok(())
+}
+
 // Initialize default data
 fn initialize_default_data(conn: &Connection) -> Result<()> {
     // Check if users table is empty
@@ -286,8 +792,8 @@ fn initialize_default_data(conn: &Connection) -> Result<()> {
         // Insert default cash register
         let register_id = Uuid::new_v4().to_string();
         conn.execute(
-            "INSERT INTO cash_registers (id, name, location, is_active) VALUES (?1, ?2, ?3, ?4)",
-            params![register_id, "Cash Register 1", "Main Store", 1]
+            "INSERT INTO cash_registers (id, name, location, is_active, code) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![register_id, "Cash Register 1", "Main Store", 1, "R1"]
         )?;
     }
     
@@ -355,25 +861,163 @@ fn initialize_default_data(conn: &Connection) -> Result<()> {
         )?;
     }
     
+    let scale_label_layouts = serde_json::to_string(&scale::default_layouts()).unwrap_or_default();
+    
+    // Default application settings; existing values are kept
+    let default_settings = vec![
+        // Discounts above this percentage of the discounted amount need manager approval
+        ("discount_approval_threshold", "10"),
+        // Whether shelf prices include PPN ("inclusive") or have it added ("exclusive")
+        ("tax_price_mode", "\"inclusive\""),
+        // Cash tenders are rounded to a multiple of this amount (e.g. 100 or 500); 0 disables it
+        ("cash_rounding_increment", "0"),
+        // How cash amounts are rounded: "nearest", "up" or "down"
+        ("cash_rounding_mode", "\"nearest\""),
+        // Parked carts are discarded after this many minutes
+        ("parked_cart_ttl_minutes", "240"),
+        // Customers earn one loyalty point per this much spend; 0 disables earning
+        ("loyalty_earn_amount", "10000"),
+        // Value of one point when redeemed as a tender
+        ("loyalty_point_value", "100"),
+        // Earned points expire after this many days; 0 keeps them forever
+        ("loyalty_expiry_days", "365"),
+        // Reorder suggestions average sales over this many days
+        ("reorder_velocity_days", "30"),
+        // Reorder suggestions cover expected sales for this many days
+        ("reorder_cover_days", "14"),
+        // Digit layouts of in-store scale labels (EAN-13), first matching prefix wins
+        ("scale_label_layouts", scale_label_layouts.as_str()),
+        // Leading digits of generated in-store EAN-13 barcodes (GS1 restricted circulation range);
+        // must not overlap a scale label prefix
+        ("internal_barcode_prefix", "\"29\""),
+    ];
+    
+    // Default payment methods; existing rows are left as configured
+    let default_payment_methods = vec![
+        // (code, display name, drawer cash, opens drawer, allows change, requires reference, sort order)
+        ("cash", "Cash", true, true, true, false, 0),
+        ("card", "Card", false, false, false, false, 1),
+        ("qris", "QRIS", false, false, false, false, 2),
+        ("mobile", "Mobile", false, false, false, false, 3),
+        ("points", "Loyalty points", false, false, false, false, 4),
+        // Card tenders take the card code as their reference
+        ("gift_card", "Gift card", false, false, false, true, 5),
+        ("store_credit", "Store credit", false, false, false, true, 6),
+        // Charged to the customer's account (kasbon)
+        ("on_account", "On account", false, false, false, false, 7),
+    ];
+    
+    for (code, display_name, is_drawer_cash, opens_drawer, allows_change, requires_reference, sort_order) in default_payment_methods {
+        conn.execute(
+            "INSERT OR IGNORE INTO payment_methods (code, display_name, is_drawer_cash, opens_drawer, allows_change, requires_reference, is_active, sort_order) VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1, ?7)",
+            params![code, display_name, is_drawer_cash, opens_drawer, allows_change, requires_reference, sort_order]
+        )?;
+    }
+    
+    // Default tax classes; rates are fractions and can be changed from settings
+    let default_tax_classes = vec![
+        ("standard", "PPN", 0.11),
+        ("reduced", "PPN (reduced)", 0.05),
+        ("exempt", "Exempt", 0.0),
+    ];
+    
+    for (code, name, rate) in default_tax_classes {
+        conn.execute(
+            "INSERT OR IGNORE INTO tax_classes (code, name, rate, is_active) VALUES (?1, ?2, ?3, 1)",
+            params![code, name, rate]
+        )?;
+    }
+    
+    for (key, value) in default_settings {
+        conn.execute(
+            "INSERT OR IGNORE INTO app_settings (key, value, updated_at) VALUES (?1, ?2, ?3)",
+            params![key, value, Utc::now().to_rfc3339()]
+        )?;
+    }
+    
+    // The first default scale layout took every code starting with 2, which
+    // included the 29 in-store barcodes
+    conn.execute(
+        "UPDATE app_settings SET value = ?1, updated_at = ?2 WHERE key = 'scale_label_layouts' AND value = ?3",
+        params![
+            scale_label_layouts,
+            Utc::now().to_rfc3339(),
+            r#"[{"prefix":"2","plu_start":2,"plu_length":5,"value_start":7,"value_length":5,"value_type":"weight","value_decimals":3}]"#
+        ]
+    )?;
+    
     Ok(())
 }
 
+// Read a JSON setting from app_settings
+fn get_setting(conn: &Connection, key: &str) -> Result<Option<serde_json::Value>, String> {
+    match conn.query_row("SELECT value FROM app_settings WHERE key = ?1", params![key], |row| row.get::<_, String>(0)) {
+        Ok(value) => Ok(serde_json::from_str(&value).ok()),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn setting_f64(conn: &Connection, key: &str, default: f64) -> Result<f64, String> {
+    Ok(get_setting(conn, key)?.and_then(|v| v.as_f64()).unwrap_or(default))
+}
+
+// Check a manager's credentials for an approval and return their user id
+fn verify_manager_approval(conn: &Connection, approval: &LoginData) -> Result<String, String> {
+    let result = conn.query_row(
+        "SELECT id, password_hash, role, is_active FROM users WHERE username = ?1",
+        params![approval.username],
+        |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, bool>(3)?))
+    );
+    
+    match result {
+        Ok((id, password_hash, role, is_active)) => {
+            let password_valid = bcrypt::verify(&approval.password, &password_hash).unwrap_or(false);
+            if password_valid && is_active && (role == "admin" || role == "manager") {
+                Ok(id)
+            } else {
+                Err("Approval requires a valid manager login".to_string())
+            }
+        },
+        Err(rusqlite::Error::QueryReturnedNoRows) => Err("Approval requires a valid manager login".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+const PRODUCT_COLUMNS: &str = "id, name, barcode, price, stock, category, tax_class, cost_price, reorder_point, reorder_quantity, supplier_id,
+    parent_id, (SELECT name FROM parent_products WHERE id = products.parent_id), variant_attributes, sold_by_weight, plu";
+
+fn map_product(row: &rusqlite::Row) -> rusqlite::Result<Product> {
+    Ok(Product {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        barcode: row.get(2)?,
+        price: row.get(3)?,
+        stock: row.get(4)?,
+        category: row.get(5)?,
+        tax_class: row.get(6)?,
+        cost_price: row.get(7)?,
+        reorder_point: row.get(8)?,
+        reorder_quantity: row.get(9)?,
+        supplier_id: row.get(10)?,
+        parent_id: row.get(11)?,
+        parent_name: row.get(12)?,
+        variant_attributes: row.get::<_, Option<String>>(13)?.and_then(|a| serde_json::from_str(&a).ok()),
+        pack_quantity: None,
+        sold_by_weight: row.get(14)?,
+        plu: row.get(15)?,
+        scale_quantity: None,
+        scale_amount: None,
+    })
+}
+
 // Tauri commands - Existing functionality
 #[tauri::command]
 fn get_products(db: State<Database>) -> Result<Vec<Product>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
-    let mut stmt = conn.prepare("SELECT id, name, barcode, price, stock, category FROM products").map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM products", PRODUCT_COLUMNS)).map_err(|e| e.to_string())?;
     
-    let product_iter = stmt.query_map([], |row| {
-        Ok(Product {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            barcode: row.get(2)?,
-            price: row.get(3)?,
-            stock: row.get(4)?,
-            category: row.get(5)?,
-        })
-    }).map_err(|e| e.to_string())?;
+    let product_iter = stmt.query_map([], map_product).map_err(|e| e.to_string())?;
     
     let mut products = Vec::new();
     for product in product_iter {
@@ -383,112 +1027,684 @@ fn get_products(db: State<Database>) -> Result<Vec<Product>, String> {
     Ok(products)
 }
 
+// Look a product up by its own barcode, an alternate one or a scale label.
+// Pack barcodes return the product priced and named for the whole pack; scale
+// labels return the weighed product with the label's weight in kg and, for
+// price labels, the printed amount the line is charged.
 #[tauri::command]
 fn get_product_by_barcode(barcode: String, db: State<Database>) -> Result<Option<Product>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
-    let mut stmt = conn.prepare("SELECT id, name, barcode, price, stock, category FROM products WHERE barcode = ?1").map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM products WHERE barcode = ?1", PRODUCT_COLUMNS)).map_err(|e| e.to_string())?;
     
-    match stmt.query_row(params![barcode], |row| {
-        Ok(Product {
-            id: row.get(0)?,
-            name: row.get(1)?,
-            barcode: row.get(2)?,
-            price: row.get(3)?,
-            stock: row.get(4)?,
-            category: row.get(5)?,
-        })
-    }) {
-        Ok(product) => Ok(Some(product)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(e.to_string()),
+    match stmt.query_row(params![barcode], map_product) {
+        Ok(product) => return Ok(Some(product)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => {},
+        Err(e) => return Err(e.to_string()),
+    }
+    
+    if let Some(pack) = barcodes::find_barcode(&conn, &barcode)? {
+        let mut product = conn.query_row(
+            &format!("SELECT {} FROM products WHERE id = ?1", PRODUCT_COLUMNS),
+            params![pack.product_id],
+            map_product
+        ).map_err(|e| e.to_string())?;
+        
+        if pack.pack_quantity > 1 {
+            product.name = format!("{} ({})", product.name, pack.label.unwrap_or_else(|| format!("x{}", pack.pack_quantity)));
+            product.price = pack.pack_price.unwrap_or_else(|| pricing::round_money(product.price * pack.pack_quantity as f64));
+        }
+        product.barcode = pack.barcode;
+        product.pack_quantity = Some(pack.pack_quantity);
+        
+        return Ok(Some(product));
     }
+    
+    let label = match scale::parse_scale_label(&barcode, &scale::scale_label_layouts(&conn)?) {
+        Some(label) => label,
+        None => return Ok(None),
+    };
+    let mut product = match conn.query_row(
+        &format!("SELECT {} FROM products WHERE sold_by_weight = 1 AND CAST(plu AS INTEGER) = ?1", PRODUCT_COLUMNS),
+        params![label.plu as i64],
+        map_product
+    ) {
+        Ok(product) => product,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+    
+    let line = scale::scale_line(&label, &product.name, product.price)?;
+    product.barcode = barcode;
+    product.scale_quantity = Some(line.quantity);
+    product.scale_amount = line.amount;
+    
+    Ok(Some(product))
+}
+
+// Pricing of a sale before tenders, shared by create_transaction and the
+// price_cart preview. `line_discounts` holds (gross, promotion, manual) per line.
+struct SalePricing {
+    applied_promotions: Vec<promotions::AppliedPromotion>,
+    line_discounts: Vec<(f64, f64, f64)>,
+    subtotal: f64,
+    order_discount: f64,
+    discount_total: f64,
+    tax_mode: tax::PriceMode,
+    line_amounts: Vec<f64>,
+    order_discount_shares: Vec<f64>,
+    line_taxes: Vec<(String, f64, tax::LineTax)>,
+    tax_total: f64,
+    total: f64,
+}
+
+// Reprice items from their shelf prices and work out promotions, discounts
+// and tax. Promotions apply first, manual line discounts to what remains.
+fn price_sale(conn: &Connection, items: &mut [TransactionItem], discount: Option<&DiscountInput>, is_member: bool) -> Result<SalePricing, String> {
+    // Scale labels fix the weight sold, and price labels the amount charged
+    let mut label_amounts = Vec::with_capacity(items.len());
+    for item in items.iter_mut() {
+        item.price = barcodes::shelf_price(conn, &item.product_id, item.barcode.as_deref())?;
+        let label = match item.barcode.as_deref() {
+            Some(barcode) => scale::label_line(conn, &item.product_id, barcode)?,
+            None => None,
+        };
+        if let Some(label) = &label {
+            item.quantity = label.quantity;
+        }
+        label_amounts.push(label.and_then(|label| label.amount));
+    }
+    let applied_promotions = promotions::apply_to_items(conn, items, is_member)?;
+    let mut line_discounts = Vec::with_capacity(items.len());
+    for (index, (item, label_amount)) in items.iter().zip(&label_amounts).enumerate() {
+        let gross = pricing::round_money(label_amount.unwrap_or(item.price * item.quantity));
+        let promotion_amount = pricing::round_money(
+            applied_promotions.iter().filter(|p| p.line_index == index).map(|p| p.amount).sum()
+        );
+        let manual_amount = match &item.line_discount {
+            Some(line_discount) => pricing::discount_amount(gross - promotion_amount, line_discount)?,
+            None => 0.0,
+        };
+        line_discounts.push((gross, promotion_amount, manual_amount));
+    }
+    
+    let subtotal = pricing::round_money(line_discounts.iter().map(|(gross, promotion, manual)| gross - promotion - manual).sum());
+    let order_discount = match discount {
+        Some(discount) => pricing::discount_amount(subtotal, discount)?,
+        None => 0.0,
+    };
+    let line_discount_total: f64 = line_discounts.iter().map(|(_, promotion, manual)| promotion + manual).sum();
+    let discount_total = pricing::round_money(line_discount_total + order_discount);
+    
+    // Tax per line on the amount after all discounts; see tax.rs for the rounding rule
+    let tax_mode = tax::price_mode(conn)?;
+    let line_amounts: Vec<f64> = line_discounts.iter()
+        .map(|(gross, promotion, manual)| pricing::round_money(gross - promotion - manual))
+        .collect();
+    let order_discount_shares = tax::allocate_discount(order_discount, &line_amounts);
+    let mut line_taxes = Vec::with_capacity(items.len());
+    for ((item, amount), share) in items.iter().zip(&line_amounts).zip(&order_discount_shares) {
+        let (tax_class, tax_rate) = tax::product_tax_rate(conn, &item.product_id)?;
+        line_taxes.push((tax_class, tax_rate, tax::line_tax(amount - share, tax_rate, tax_mode)));
+    }
+    let tax_total = pricing::round_money(line_taxes.iter().map(|(_, _, line_tax)| line_tax.tax_amount).sum());
+    
+    let total = match tax_mode {
+        tax::PriceMode::Inclusive => pricing::round_money(subtotal - order_discount),
+        tax::PriceMode::Exclusive => pricing::round_money(subtotal - order_discount + tax_total),
+    };
+    
+    Ok(SalePricing {
+        applied_promotions,
+        line_discounts,
+        subtotal,
+        order_discount,
+        discount_total,
+        tax_mode,
+        line_amounts,
+        order_discount_shares,
+        line_taxes,
+        tax_total,
+        total,
+    })
 }
 
 #[tauri::command]
-fn create_transaction(items: Vec<TransactionItem>, payment_method: String, db: State<Database>) -> Result<String, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+#[allow(clippy::too_many_arguments)]
+fn create_transaction(items: Vec<TransactionItem>, payment_method: String, shift_id: Option<String>, discount: Option<DiscountInput>, payments: Option<Vec<PaymentInput>>, customer_id: Option<String>, app: AppHandle, db: State<Database>) -> Result<String, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    // The sale, its receipt number and the stock updates commit together so
+    // that a failed sale never consumes a receipt number
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
     let transaction_id = Uuid::new_v4().to_string();
-    let total: f64 = items.iter().map(|item| item.subtotal).sum();
     let timestamp = Utc::now().to_rfc3339();
     
+    // Member-only promotions apply to customers flagged as members
+    let is_member = customers::is_member(&tx, customer_id.as_deref())?;
+    
+    // Line totals are recomputed from the stored shelf prices; client-side
+    // prices and subtotals are not trusted
+    let mut items = items;
+    let SalePricing {
+        applied_promotions,
+        line_discounts,
+        subtotal,
+        order_discount,
+        discount_total,
+        tax_mode,
+        line_amounts,
+        order_discount_shares,
+        line_taxes,
+        tax_total,
+        total,
+    } = price_sale(&tx, &mut items, discount.as_ref(), is_member)?;
+    let discount_approved_by = approve_manual_discounts(&tx, &items, discount.as_ref(), &line_discounts, order_discount)?;
+    let loyalty_lines: Vec<(String, f64)> = items.iter().zip(&line_amounts).zip(&order_discount_shares)
+        .map(|((item, amount), share)| (item.product_id.clone(), amount - share))
+        .collect();
+    
+    // Tenders must cover the total; without explicit tenders the whole sale is
+    // paid with `payment_method`. Cash rounding is added to the stored total so
+    // that the total always matches what was collected.
+    let payment_methods = payments::load_payment_methods(&tx)?;
+    let cash_rounding = payments::cash_rounding(&tx)?;
+    let tenders = payments.unwrap_or_else(|| vec![PaymentInput {
+        method: payment_method.clone(),
+        amount: payments::amount_due(total, &payment_method, &payment_methods, &cash_rounding),
+        reference: None,
+    }]);
+    let allocation = payments::allocate_tenders(total, &tenders, &payment_methods, &cash_rounding)?;
+    let rounding_amount = allocation.rounding_amount;
+    let total = pricing::round_money(total + rounding_amount);
+    let payment_lines = allocation.payments;
+    let payment_method = match payment_lines.first() {
+        Some(first) if payment_lines.iter().all(|p| p.method == first.method) => first.method.clone(),
+        _ => "split".to_string(),
+    };
+    
+    // Record which cashier and register made the sale when it belongs to a shift
+    let (user_id, cash_register_id): (Option<String>, String) = match &shift_id {
+        Some(shift_id) => {
+            let (user_id, register_id, status): (String, String, String) = tx.query_row(
+                "SELECT user_id, cash_register_id, status FROM cash_shifts WHERE id = ?1",
+                params![shift_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            ).map_err(|e| e.to_string())?;
+            
+            if status != "open" {
+                return Err("Shift is not open".to_string());
+            }
+            
+            (Some(user_id), register_id)
+        },
+        None => {
+            let register_id: String = tx.query_row(
+                "SELECT id FROM cash_registers WHERE is_active = 1 LIMIT 1",
+                [],
+                |row| row.get(0)
+            ).map_err(|e| e.to_string())?;
+            
+            (None, register_id)
+        },
+    };
+    
+    let receipt_number = next_receipt_number(&tx, &cash_register_id)?;
+    
     // Insert transaction
-    conn.execute(
-        "INSERT INTO transactions (id, total, timestamp, payment_method) VALUES (?1, ?2, ?3, ?4)",
-        params![transaction_id, total, timestamp, payment_method]
+    tx.execute(
+        "INSERT INTO transactions (id, total, timestamp, payment_method, user_id, shift_id, cash_register_id, receipt_number, subtotal, discount_total, tax_total, tax_mode, rounding_amount, customer_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        params![transaction_id, total, timestamp, payment_method, user_id, shift_id, cash_register_id, receipt_number, subtotal, discount_total, tax_total, tax_mode.as_str(), rounding_amount, customer_id]
     ).map_err(|e| e.to_string())?;
     
+    if let Some(discount) = &discount {
+        record_discount(&tx, &transaction_id, None, discount, order_discount, discount_approved_by.as_deref(), &timestamp)?;
+    }
+    
+    payments::record_payments(&tx, &transaction_id, shift_id.as_deref(), user_id.as_deref(), &payment_lines, &payment_methods, &timestamp)?;
+    loyalty::record_sale(&tx, &transaction_id, customer_id.as_deref(), &loyalty_lines, &payment_lines, total, user_id.as_deref(), &timestamp)?;
+    giftcards::redeem_tenders(&tx, &transaction_id, &payment_lines, user_id.as_deref())?;
+    receivables::charge_tenders(&tx, &transaction_id, customer_id.as_deref(), &payment_lines, user_id.as_deref(), &timestamp)?;
+    
     // Insert transaction items and update stock
-    for item in &items {
+    let mut low_stock_alerts = Vec::new();
+    for (index, (item, ((gross, promotion_amount, manual_amount), (tax_class, tax_rate, line_tax)))) in items.iter().zip(line_discounts.into_iter().zip(line_taxes)).enumerate() {
         // Insert transaction item
         let item_id = Uuid::new_v4().to_string();
-        conn.execute(
-            "INSERT INTO transaction_items (id, transaction_id, product_id, name, quantity, price, subtotal) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            params![item_id, transaction_id, item.product_id, item.name, item.quantity, item.price, item.subtotal]
+        let line_discount = pricing::round_money(promotion_amount + manual_amount);
+        let pack_quantity = barcodes::pack_quantity(&tx, &item.product_id, item.barcode.as_deref())?;
+        let units = scale::stock_units(&tx, &item.product_id, item.quantity, pack_quantity)?;
+        // Unit cost is per sold quantity: per pack, or per kg for weighed products
+        tx.execute(
+            "INSERT INTO transaction_items (id, transaction_id, product_id, name, quantity, price, subtotal, discount_amount, tax_class, tax_rate, taxable_amount, tax_amount, barcode, pack_quantity, stock_units, unit_cost)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, (SELECT cost_price * CASE WHEN sold_by_weight THEN 1000 ELSE ?14 END FROM products WHERE id = ?3))",
+            params![item_id, transaction_id, item.product_id, item.name, item.quantity, item.price, pricing::round_money(gross - line_discount), line_discount, tax_class, tax_rate, line_tax.taxable_amount, line_tax.tax_amount, item.barcode, pack_quantity, units]
         ).map_err(|e| e.to_string())?;
         
-        // Update product stock
-        conn.execute(
-            "UPDATE products SET stock = stock - ?1 WHERE id = ?2",
-            params![item.quantity, item.product_id]
-        ).map_err(|e| e.to_string())?;
+        for applied in applied_promotions.iter().filter(|p| p.line_index == index) {
+            tx.execute(
+                "INSERT INTO transaction_discounts (id, transaction_id, transaction_item_id, scope, discount_type, value, amount, reason, promotion_id, created_at) VALUES (?1, ?2, ?3, 'promotion', ?4, ?5, ?5, ?6, ?7, ?8)",
+                params![Uuid::new_v4().to_string(), transaction_id, item_id, applied.promo_type, applied.amount, applied.name, applied.promotion_id, timestamp]
+            ).map_err(|e| e.to_string())?;
+        }
+        
+        if let Some(discount) = &item.line_discount {
+            record_discount(&tx, &transaction_id, Some(&item_id), discount, manual_amount, discount_approved_by.as_deref(), &timestamp)?;
+        }
+        
+        let stock_after = inventory::record_movement(&tx, &item.product_id, "sale", -units, Some("transaction"), Some(&transaction_id), user_id.as_deref(), None)?;
+        if let Some(alert) = reorder::low_stock_alert(&tx, &item.product_id, stock_after + units, stock_after)? {
+            low_stock_alerts.push(alert);
+        }
+    }
+    
+    tx.commit().map_err(|e| e.to_string())?;
+    
+    // Alerts are only a notification; a failed emit does not undo the sale
+    for alert in low_stock_alerts {
+        let _ = app.emit(reorder::LOW_STOCK_EVENT, alert);
     }
     
     Ok(transaction_id)
 }
 
+// Void a completed sale: put its items back in stock, return card, points and
+// account tenders to the customer and take its drawer cash out of the shift. With
+// `store_credit` the money is refunded as a store credit card instead and the
+// drawer is left alone. Only sales from an open shift (or made outside shifts)
+// can be voided, with a manager's approval.
 #[tauri::command]
-fn get_transactions(db: State<Database>) -> Result<Vec<Transaction>, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
+fn void_transaction(transaction_id: String, reason: String, approval: LoginData, store_credit: Option<bool>, db: State<Database>) -> Result<Option<giftcards::GiftCard>, String> {
+    if reason.trim().is_empty() {
+        return Err("A reason is required to void a sale".to_string());
+    }
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let approved_by = verify_manager_approval(&tx, &approval)?;
+    let timestamp = Utc::now().to_rfc3339();
     
-    // Get transactions
-    let mut stmt = conn.prepare("SELECT id, total, timestamp, payment_method FROM transactions ORDER BY timestamp DESC").map_err(|e| e.to_string())?;
+    let (shift_id, receipt_number, voided_at, customer_id): (Option<String>, Option<String>, Option<String>, Option<String>) = match tx.query_row(
+        "SELECT shift_id, receipt_number, voided_at, customer_id FROM transactions WHERE id = ?1",
+        params![transaction_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+    ) {
+        Ok(row) => row,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Err("Transaction not found".to_string()),
+        Err(e) => return Err(e.to_string()),
+    };
     
-    let mut transactions = Vec::new();
-    let transaction_iter = stmt.query_map([], |row| {
-        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get::<_, String>(3)?))
-    }).map_err(|e| e.to_string())?;
+    if voided_at.is_some() {
+        return Err("Transaction is already voided".to_string());
+    }
     
-    for transaction_result in transaction_iter {
-        let (id, total, timestamp, payment_method) = transaction_result.map_err(|e| e.to_string())?;
+    if let Some(shift_id) = &shift_id {
+        let status: String = tx.query_row(
+            "SELECT status FROM cash_shifts WHERE id = ?1",
+            params![shift_id],
+            |row| row.get(0)
+        ).map_err(|e| e.to_string())?;
         
-        // Get transaction items
-        let mut item_stmt = conn.prepare("SELECT product_id, name, quantity, price, subtotal FROM transaction_items WHERE transaction_id = ?1").map_err(|e| e.to_string())?;
-        let mut items = Vec::new();
+        if status != "open" {
+            return Err("Sales from a closed shift cannot be voided".to_string());
+        }
+    }
+    
+    tx.execute(
+        "UPDATE transactions SET voided_at = ?1, voided_by = ?2, void_reason = ?3 WHERE id = ?4",
+        params![timestamp, approved_by, reason.trim(), transaction_id]
+    ).map_err(|e| e.to_string())?;
+    
+    let void_label = format!("Void {}", receipt_number.as_deref().unwrap_or(&transaction_id));
+    
+    let sold_items = tx.prepare("SELECT product_id, stock_units FROM transaction_items WHERE transaction_id = ?1")
+        .map_err(|e| e.to_string())?
+        .query_map(params![transaction_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i32>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    for (product_id, quantity) in sold_items {
+        inventory::record_movement(&tx, &product_id, "return", quantity, Some("transaction"), Some(&transaction_id), Some(&approved_by), Some(&void_label))?;
+    }
+    
+    loyalty::reverse_sale(&tx, &transaction_id, &void_label, Some(&approved_by))?;
+    giftcards::refund_redemptions(&tx, &transaction_id, &void_label, Some(&approved_by))?;
+    receivables::reverse_sale(&tx, &transaction_id, &void_label, Some(&approved_by))?;
+    
+    // Everything not paid with cards, points or on account is refunded as store credit
+    let credit_card = if store_credit.unwrap_or(false) {
+        let amount: f64 = tx.query_row(
+            "SELECT COALESCE(SUM(amount), 0.0) FROM payments
+             WHERE transaction_id = ?1 AND method NOT IN (?2, ?3, ?4, ?5)",
+            params![transaction_id, giftcards::GIFT_CARD_METHOD, giftcards::STORE_CREDIT_METHOD, loyalty::POINTS_METHOD, receivables::ON_ACCOUNT_METHOD],
+            |row| row.get(0)
+        ).map_err(|e| e.to_string())?;
         
-        let item_iter = item_stmt.query_map(params![id], |row| {
-            Ok(TransactionItem {
-                product_id: row.get(0)?,
-                name: row.get(1)?,
-                quantity: row.get(2)?,
-                price: row.get(3)?,
-                subtotal: row.get(4)?,
-            })
-        }).map_err(|e| e.to_string())?;
+        if amount > 0.0 {
+            Some(giftcards::issue_card(&tx, None, giftcards::STORE_CREDIT_METHOD, amount, customer_id.as_deref(), None, Some(&transaction_id), None, Some(&approved_by))?)
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+    
+    if let (Some(shift_id), false) = (&shift_id, store_credit.unwrap_or(false)) {
+        let cash: f64 = tx.query_row(
+            "SELECT COALESCE(SUM(p.amount), 0.0)
+             FROM payments p
+             JOIN payment_methods pm ON pm.code = p.method
+             WHERE p.transaction_id = ?1 AND pm.is_drawer_cash = 1",
+            params![transaction_id],
+            |row| row.get(0)
+        ).map_err(|e| e.to_string())?;
         
-        for item in item_iter {
-            items.push(item.map_err(|e| e.to_string())?);
+        if cash > 0.0 {
+            tx.execute(
+                "INSERT INTO cash_movements (id, shift_id, transaction_id, movement_type, amount, reason, timestamp, user_id) VALUES (?1, ?2, ?3, 'void', ?4, ?5, ?6, ?7)",
+                params![Uuid::new_v4().to_string(), shift_id, transaction_id, cash, reason.trim(), timestamp, approved_by]
+            ).map_err(|e| e.to_string())?;
+            
+            tx.execute(
+                "UPDATE cash_shifts SET expected_cash = expected_cash - ?1 WHERE id = ?2",
+                params![cash, shift_id]
+            ).map_err(|e| e.to_string())?;
         }
+    }
+    
+    tx.commit().map_err(|e| e.to_string())?;
+    
+    Ok(credit_card)
+}
+
+// Manual line and order discounts together, as a share of the sale before any
+// discount, need a manager's approval above `discount_approval_threshold`.
+// An approval given on any of the discounts covers the whole sale; every
+// approval given is checked. Returns the approving manager.
+fn approve_manual_discounts(conn: &Connection, items: &[TransactionItem], discount: Option<&DiscountInput>, line_discounts: &[(f64, f64, f64)], order_discount: f64) -> Result<Option<String>, String> {
+    let approval_threshold = setting_f64(conn, "discount_approval_threshold", 10.0)?;
+    let gross: f64 = line_discounts.iter().map(|(gross, _, _)| gross).sum();
+    let manual: f64 = line_discounts.iter().map(|(_, _, manual)| manual).sum::<f64>() + order_discount;
+    let percent = pricing::discount_percent(gross, manual);
+    
+    let mut approved_by = None;
+    let approvals = discount.into_iter()
+        .chain(items.iter().filter_map(|item| item.line_discount.as_ref()))
+        .filter_map(|d| d.approval.as_ref());
+    for approval in approvals {
+        let manager = verify_manager_approval(conn, approval)?;
+        approved_by.get_or_insert(manager);
+    }
+    
+    if approved_by.is_none() && percent > approval_threshold {
+        return Err(format!("A discount of {:.1}% requires manager approval", percent));
+    }
+    
+    Ok(approved_by)
+}
+
+// Store a discount applied to a sale with the manager who approved it, if any
+fn record_discount(conn: &Connection, transaction_id: &str, transaction_item_id: Option<&str>, discount: &DiscountInput, amount: f64, approved_by: Option<&str>, timestamp: &str) -> Result<(), String> {
+    let scope = if transaction_item_id.is_some() { "line" } else { "order" };
+    
+    conn.execute(
+        "INSERT INTO transaction_discounts (id, transaction_id, transaction_item_id, scope, discount_type, value, amount, reason, approved_by, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![Uuid::new_v4().to_string(), transaction_id, transaction_item_id, scope, discount.discount_type, discount.value, amount, discount.reason, approved_by, timestamp]
+    ).map_err(|e| e.to_string())?;
+    
+    Ok(())
+}
+
+// Allocate the next receipt number for a register (e.g. R1-000123). Must be
+// called inside the sale's database transaction to keep numbers gap-free.
+fn next_receipt_number(conn: &Connection, cash_register_id: &str) -> Result<String, String> {
+    conn.execute(
+        "UPDATE cash_registers SET receipt_counter = receipt_counter + 1 WHERE id = ?1",
+        params![cash_register_id]
+    ).map_err(|e| e.to_string())?;
+    
+    let (code, counter): (String, i64) = conn.query_row(
+        "SELECT code, receipt_counter FROM cash_registers WHERE id = ?1",
+        params![cash_register_id],
+        |row| Ok((row.get(0)?, row.get(1)?))
+    ).map_err(|e| e.to_string())?;
+    
+    Ok(format!("{}-{:06}", code, counter))
+}
+
+// Transaction history search; all filters are optional. Results are ordered
+// newest first and paged with an opaque cursor taken from `next_cursor`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TransactionSearch {
+    start_date: Option<String>,
+    end_date: Option<String>,
+    payment_method: Option<String>,
+    user_id: Option<String>,
+    shift_id: Option<String>,
+    customer_id: Option<String>,
+    min_total: Option<f64>,
+    max_total: Option<f64>,
+    product_id: Option<String>,
+    cursor: Option<String>,
+    limit: Option<i64>,
+}
+
+// One page of transaction history
+#[derive(Debug, Serialize, Deserialize)]
+struct TransactionPage {
+    transactions: Vec<Transaction>,
+    next_cursor: Option<String>,
+}
+
+// Columns every transaction query selects for load_transactions_with_items
+const TRANSACTION_COLUMNS: &str = "id, total, timestamp, payment_method, user_id, shift_id, cash_register_id, receipt_number, reprint_count, subtotal, discount_total, tax_total, tax_mode, rounding_amount, customer_id, voided_at";
+
+// Load transactions together with their items in a single joined query.
+// `page_sql` must select TRANSACTION_COLUMNS from transactions.
+fn load_transactions_with_items(conn: &Connection, page_sql: &str, values: Vec<rusqlite::types::Value>) -> Result<Vec<Transaction>, String> {
+    let sql = format!(
+        "SELECT t.id, t.total, t.timestamp, t.payment_method, t.user_id, t.shift_id, t.cash_register_id,
+                t.receipt_number, t.reprint_count, COALESCE(t.subtotal, t.total), t.discount_total, t.tax_total, t.tax_mode,
+                t.rounding_amount, t.customer_id, t.voided_at, ti.product_id, ti.name, ti.quantity, ti.price, ti.subtotal, ti.discount_amount, ti.tax_class, ti.tax_rate, ti.tax_amount,
+                ti.barcode, ti.pack_quantity
+         FROM ({}) t
+         LEFT JOIN transaction_items ti ON ti.transaction_id = t.id
+         ORDER BY t.timestamp DESC, t.id DESC, ti.rowid",
+        page_sql
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let mut rows = stmt.query(rusqlite::params_from_iter(values.clone())).map_err(|e| e.to_string())?;
+    
+    let mut transactions: Vec<Transaction> = Vec::new();
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let id: String = row.get(0).map_err(|e| e.to_string())?;
         
-        transactions.push(Transaction {
-            id,
-            items,
-            total,
-            timestamp,
-            payment_method,
-        });
+        if transactions.last().map(|t| t.id != id).unwrap_or(true) {
+            transactions.push(Transaction {
+                id,
+                items: Vec::new(),
+                total: row.get(1).map_err(|e| e.to_string())?,
+                timestamp: row.get(2).map_err(|e| e.to_string())?,
+                payment_method: row.get(3).map_err(|e| e.to_string())?,
+                user_id: row.get(4).map_err(|e| e.to_string())?,
+                shift_id: row.get(5).map_err(|e| e.to_string())?,
+                cash_register_id: row.get(6).map_err(|e| e.to_string())?,
+                receipt_number: row.get(7).map_err(|e| e.to_string())?,
+                reprint_count: row.get(8).map_err(|e| e.to_string())?,
+                subtotal: row.get(9).map_err(|e| e.to_string())?,
+                discount_total: row.get(10).map_err(|e| e.to_string())?,
+                tax_total: row.get(11).map_err(|e| e.to_string())?,
+                tax_mode: row.get(12).map_err(|e| e.to_string())?,
+                payments: Vec::new(),
+                rounding_amount: row.get(13).map_err(|e| e.to_string())?,
+                customer_id: row.get(14).map_err(|e| e.to_string())?,
+                voided_at: row.get(15).map_err(|e| e.to_string())?,
+            });
+        }
+        
+        // Transactions without items come back with NULL item columns
+        if let Some(product_id) = row.get::<_, Option<String>>(16).map_err(|e| e.to_string())? {
+            if let Some(transaction) = transactions.last_mut() {
+                transaction.items.push(TransactionItem {
+                    product_id,
+                    name: row.get(17).map_err(|e| e.to_string())?,
+                    quantity: row.get(18).map_err(|e| e.to_string())?,
+                    price: row.get(19).map_err(|e| e.to_string())?,
+                    subtotal: row.get(20).map_err(|e| e.to_string())?,
+                    discount_amount: row.get(21).map_err(|e| e.to_string())?,
+                    line_discount: None,
+                    tax_class: row.get(22).map_err(|e| e.to_string())?,
+                    tax_rate: row.get(23).map_err(|e| e.to_string())?,
+                    tax_amount: row.get(24).map_err(|e| e.to_string())?,
+                    barcode: row.get(25).map_err(|e| e.to_string())?,
+                    pack_quantity: row.get(26).map_err(|e| e.to_string())?,
+                });
+            }
+        }
+    }
+    
+    // Tender lines for the same transactions
+    let payment_sql = format!(
+        "SELECT transaction_id, method, amount, tendered, change_given, reference
+         FROM payments
+         WHERE transaction_id IN (SELECT id FROM ({}))
+         ORDER BY rowid",
+        page_sql
+    );
+    for (transaction_id, payment) in payments::load_payments(conn, &payment_sql, values)? {
+        if let Some(transaction) = transactions.iter_mut().find(|t| t.id == transaction_id) {
+            transaction.payments.push(payment);
+        }
     }
     
     Ok(transactions)
 }
 
 #[tauri::command]
-fn update_product_stock(product_id: String, new_stock: i32, db: State<Database>) -> Result<(), String> {
+fn get_transactions(db: State<Database>) -> Result<Vec<Transaction>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     
-    conn.execute(
-        "UPDATE products SET stock = ?1 WHERE id = ?2",
-        params![new_stock, product_id]
-    ).map_err(|e| e.to_string())?;
+    load_transactions_with_items(
+        &conn,
+        &format!("SELECT {} FROM transactions", TRANSACTION_COLUMNS),
+        Vec::new()
+    )
+}
+
+#[tauri::command]
+fn search_transactions(search: TransactionSearch, db: State<Database>) -> Result<TransactionPage, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    
+    query_transactions(&conn, search)
+}
+
+fn query_transactions(conn: &Connection, search: TransactionSearch) -> Result<TransactionPage, String> {
+    use rusqlite::types::Value;
+    
+    let limit = search.limit.unwrap_or(50).clamp(1, 500);
+    
+    let mut conditions: Vec<String> = Vec::new();
+    let mut values: Vec<Value> = Vec::new();
+    
+    if let Some(start_date) = search.start_date {
+        values.push(Value::Text(start_date));
+        conditions.push(format!("timestamp >= ?{}", values.len()));
+    }
+    if let Some(end_date) = search.end_date {
+        values.push(Value::Text(end_date));
+        conditions.push(format!("timestamp < ?{}", values.len()));
+    }
+    // Split sales are stored as "split", so also match any of their tenders
+    if let Some(payment_method) = search.payment_method {
+        values.push(Value::Text(payment_method));
+        conditions.push(format!(
+            "(payment_method = ?{0} OR EXISTS (SELECT 1 FROM payments p WHERE p.transaction_id = transactions.id AND p.method = ?{0}))",
+            values.len()
+        ));
+    }
+    if let Some(user_id) = search.user_id {
+        values.push(Value::Text(user_id));
+        conditions.push(format!("user_id = ?{}", values.len()));
+    }
+    if let Some(shift_id) = search.shift_id {
+        values.push(Value::Text(shift_id));
+        conditions.push(format!("shift_id = ?{}", values.len()));
+    }
+    if let Some(customer_id) = search.customer_id {
+        values.push(Value::Text(customer_id));
+        conditions.push(format!("customer_id = ?{}", values.len()));
+    }
+    if let Some(min_total) = search.min_total {
+        values.push(Value::Real(min_total));
+        conditions.push(format!("total >= ?{}", values.len()));
+    }
+    if let Some(max_total) = search.max_total {
+        values.push(Value::Real(max_total));
+        conditions.push(format!("total <= ?{}", values.len()));
+    }
+    if let Some(product_id) = search.product_id {
+        values.push(Value::Text(product_id));
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM transaction_items pi WHERE pi.transaction_id = transactions.id AND pi.product_id = ?{})",
+            values.len()
+        ));
+    }
+    // The cursor is the (timestamp, id) of the last row on the previous page
+    if let Some(cursor) = search.cursor {
+        let (timestamp, id) = cursor.split_once('|').ok_or_else(|| "Invalid cursor".to_string())?;
+        values.push(Value::Text(timestamp.to_string()));
+        values.push(Value::Text(id.to_string()));
+        conditions.push(format!(
+            "(timestamp < ?{0} OR (timestamp = ?{0} AND id < ?{1}))",
+            values.len() - 1,
+            values.len()
+        ));
+    }
+    
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    
+    // Fetch one extra row to know whether another page exists
+    values.push(Value::Integer(limit + 1));
+    let page_sql = format!(
+        "SELECT {}
+         FROM transactions
+         {}
+         ORDER BY timestamp DESC, id DESC
+         LIMIT ?{}",
+        TRANSACTION_COLUMNS,
+        where_clause,
+        values.len()
+    );
+    
+    let mut transactions = load_transactions_with_items(conn, &page_sql, values)?;
+    
+    let next_cursor = if transactions.len() as i64 > limit {
+        transactions.truncate(limit as usize);
+        transactions.last().map(|t| format!("{}|{}", t.timestamp, t.id))
+    } else {
+        None
+    };
+    
+    Ok(TransactionPage { transactions, next_cursor })
+}
+
+// Set a product's stock to a counted figure; the difference is posted to the
+// movement ledger as an adjustment
+#[tauri::command]
+fn update_product_stock(product_id: String, new_stock: i32, reason: Option<String>, user_id: Option<String>, db: State<Database>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    
+    let current_stock: i32 = conn.query_row(
+        "SELECT stock FROM products WHERE id = ?1",
+        params![product_id],
+        |row| row.get(0)
+    ).map_err(|_| format!("Product {} not found", product_id))?;
+    
+    if new_stock != current_stock {
+        let reason = reason.filter(|r| !r.trim().is_empty()).unwrap_or_else(|| "Manual stock update".to_string());
+        inventory::record_movement(&conn, &product_id, "adjustment", new_stock - current_stock, None, None, user_id.as_deref(), Some(&reason))?;
+    }
     
     Ok(())
 }
@@ -757,28 +1973,109 @@ fn get_printer_settings(db: State<Database>) -> Result<Vec<PrinterSettings>, Str
 }
 
 #[tauri::command]
-fn print_receipt(transaction: Transaction, store_name: String, store_address: String) -> Result<(), String> {
-    // This is a placeholder for actual printing logic
-    // In a real implementation, you would interface with the system's print API
-    println!("=== {} ===", store_name);
-    println!("{}", store_address);
-    println!("------------------------");
-    println!("Transaction ID: {}", transaction.id);
-    println!("Date: {}", transaction.timestamp);
-    println!("------------------------");
+fn get_app_settings(db: State<Database>) -> Result<serde_json::Value, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare("SELECT key, value FROM app_settings ORDER BY key").map_err(|e| e.to_string())?;
     
-    for item in &transaction.items {
-        println!("{} x{} - ${:.2}", item.name, item.quantity, item.subtotal);
+    let setting_iter = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    }).map_err(|e| e.to_string())?;
+    
+    let mut settings = serde_json::Map::new();
+    for setting in setting_iter {
+        let (key, value) = setting.map_err(|e| e.to_string())?;
+        settings.insert(key, serde_json::from_str(&value).unwrap_or(serde_json::Value::Null));
     }
     
-    println!("------------------------");
-    println!("TOTAL: ${:.2}", transaction.total);
-    println!("Payment: {}", transaction.payment_method);
-    println!("Thank you for your business!");
+    Ok(serde_json::Value::Object(settings))
+}
+
+#[tauri::command]
+fn update_app_setting(key: String, value: serde_json::Value, db: State<Database>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let value_json = serde_json::to_string(&value).map_err(|e| e.to_string())?;
+    
+    // Scale labels and generated in-store barcodes must keep apart
+    match key.as_str() {
+        "scale_label_layouts" => {
+            let layouts: Vec<scale::ScaleLabelLayout> = serde_json::from_value(value.clone())
+                .map_err(|e| format!("Invalid scale label layouts: {}", e))?;
+            scale::check_prefixes(&layouts, &barcodes::internal_prefix(&conn)?)?;
+        },
+        "internal_barcode_prefix" => {
+            let prefix = value.as_str().ok_or_else(|| "Internal barcode prefix must be text".to_string())?;
+            barcodes::validate_internal_prefix(prefix)?;
+            scale::check_prefixes(&scale::scale_label_layouts(&conn)?, prefix)?;
+        },
+        _ => {},
+    }
+    
+    conn.execute(
+        "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+        params![key, value_json, Utc::now().to_rfc3339()]
+    ).map_err(|e| e.to_string())?;
     
     Ok(())
 }
 
+#[tauri::command]
+fn print_receipt(transaction: Transaction, store_name: String, store_address: String, printer_id: Option<String>, db: State<Database>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    
+    let printer = load_printer_settings(&conn, printer_id.as_deref(), "receipt")?;
+    let mut doc = printing::render_receipt(&transaction, &store_name, &store_address, false, printing::line_width(&printer.config));
+    
+    // Kick the drawer when any tender uses a method that opens it
+    let methods = payments::load_payment_methods(&conn)?;
+    let mut tender_methods: Vec<&str> = transaction.payments.iter().map(|p| p.method.as_str()).collect();
+    if tender_methods.is_empty() {
+        tender_methods.push(&transaction.payment_method);
+    }
+    doc.open_drawer = methods.iter().any(|m| m.opens_drawer && tender_methods.contains(&m.code.as_str()));
+    
+    printing::print_document(&printer, &doc)
+}
+
+fn find_transaction_by_receipt_number(conn: &Connection, receipt_number: &str) -> Result<Option<Transaction>, String> {
+    let transactions = load_transactions_with_items(
+        conn,
+        &format!("SELECT {} FROM transactions WHERE receipt_number = ?1", TRANSACTION_COLUMNS),
+        vec![rusqlite::types::Value::Text(receipt_number.to_string())]
+    )?;
+    
+    Ok(transactions.into_iter().next())
+}
+
+#[tauri::command]
+fn get_transaction_by_receipt_number(receipt_number: String, db: State<Database>) -> Result<Option<Transaction>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    
+    find_transaction_by_receipt_number(&conn, receipt_number.trim())
+}
+
+// Reprint a stored sale marked as a copy; every reprint that reaches the
+// printer is counted on the transaction
+#[tauri::command]
+fn reprint_receipt(receipt_number: String, store_name: String, store_address: String, printer_id: Option<String>, db: State<Database>) -> Result<Transaction, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    
+    let mut transaction = find_transaction_by_receipt_number(&conn, receipt_number.trim())?
+        .ok_or_else(|| format!("Receipt {} not found", receipt_number.trim()))?;
+    
+    let printer = load_printer_settings(&conn, printer_id.as_deref(), "receipt")?;
+    let doc = printing::render_receipt(&transaction, &store_name, &store_address, true, printing::line_width(&printer.config));
+    printing::print_document(&printer, &doc)?;
+    
+    conn.execute(
+        "UPDATE transactions SET reprint_count = reprint_count + 1 WHERE id = ?1",
+        params![transaction.id]
+    ).map_err(|e| e.to_string())?;
+    transaction.reprint_count += 1;
+    
+    Ok(transaction)
+}
+
 // Shift Report Data Structure
 #[derive(Debug, Serialize, Deserialize)]
 struct ShiftReportData {
@@ -786,15 +2083,18 @@ struct ShiftReportData {
     cash_summary: serde_json::Value,
     movements: Vec<serde_json::Value>,
     transactions: Vec<serde_json::Value>,
+    #[serde(default)]
+    tax_summary: Vec<serde_json::Value>,
+    #[serde(default)]
+    tender_summary: Vec<serde_json::Value>,
 }
 
-#[tauri::command]
-fn generate_shift_report(shift_id: String, user_id: String, db: State<Database>) -> Result<ShiftReport, String> {
-    let conn = db.0.lock().map_err(|e| e.to_string())?;
-    
+// Collect the shift, cash movement and transaction data shared by every report type
+fn build_shift_report_data(conn: &Connection, shift_id: &str) -> Result<ShiftReportData, String> {
     // Get shift info
     let shift_info: serde_json::Value = conn.query_row(
-        "SELECT cs.*, u.full_name as user_name, cr.name as register_name
+        "SELECT cs.id, cs.user_id, u.full_name, cs.cash_register_id, cr.name, cs.start_time, cs.end_time,
+                cs.initial_cash, cs.expected_cash, cs.actual_cash, cs.difference, cs.status, cs.notes
          FROM cash_shifts cs
          JOIN users u ON cs.user_id = u.id
          JOIN cash_registers cr ON cs.cash_register_id = cr.id
@@ -804,24 +2104,24 @@ fn generate_shift_report(shift_id: String, user_id: String, db: State<Database>)
             Ok(serde_json::json!({
                 "id": row.get::<_, String>(0)?,
                 "user_id": row.get::<_, String>(1)?,
-                "user_name": row.get::<_, String>(14)?,
+                "user_name": row.get::<_, String>(2)?,
                 "cash_register_id": row.get::<_, String>(3)?,
-                "register_name": row.get::<_, String>(15)?,
-                "start_time": row.get::<_, String>(4)?,
-                "end_time": row.get::<_, Option<String>>(5)?,
-                "initial_cash": row.get::<_, f64>(6)?,
-                "expected_cash": row.get::<_, f64>(7)?,
-                "actual_cash": row.get::<_, Option<f64>>(8)?,
-                "difference": row.get::<_, Option<f64>>(9)?,
-                "status": row.get::<_, String>(10)?,
-                "notes": row.get::<_, Option<String>>(11)?,
+                "register_name": row.get::<_, String>(4)?,
+                "start_time": row.get::<_, String>(5)?,
+                "end_time": row.get::<_, Option<String>>(6)?,
+                "initial_cash": row.get::<_, f64>(7)?,
+                "expected_cash": row.get::<_, f64>(8)?,
+                "actual_cash": row.get::<_, Option<f64>>(9)?,
+                "difference": row.get::<_, Option<f64>>(10)?,
+                "status": row.get::<_, String>(11)?,
+                "notes": row.get::<_, Option<String>>(12)?,
             }))
         }
     ).map_err(|e| e.to_string())?;
     
     // Get cash movements
     let movements: Vec<serde_json::Value> = conn.prepare(
-        "SELECT cm.*, u.full_name as user_name
+        "SELECT cm.id, cm.shift_id, cm.transaction_id, cm.movement_type, cm.amount, cm.reason, cm.timestamp, cm.user_id, u.full_name as user_name
          FROM cash_movements cm
          JOIN users u ON cm.user_id = u.id
          WHERE cm.shift_id = ?1
@@ -850,11 +2150,11 @@ fn generate_shift_report(shift_id: String, user_id: String, db: State<Database>)
         .sum();
     
     let total_cash_out: f64 = movements.iter()
-        .filter(|m| m["movement_type"] == "cash_out" || m["movement_type"] == "adjustment")
+        .filter(|m| m["movement_type"] == "cash_out" || m["movement_type"] == "adjustment" || m["movement_type"] == "void")
         .map(|m| m["amount"].as_f64().unwrap_or(0.0))
         .sum();
     
-    let cash_summary = serde_json::json!({
+    let mut cash_summary = serde_json::json!({
         "total_cash_in": total_cash_in,
         "total_cash_out": total_cash_out,
         "net_movement": total_cash_in - total_cash_out,
@@ -864,45 +2164,167 @@ fn generate_shift_report(shift_id: String, user_id: String, db: State<Database>)
         "difference": shift_info["difference"],
     });
     
-    // Get transactions for this shift (based on cash movements with transaction_id)
-    let transaction_ids: Vec<String> = movements.iter()
-        .filter_map(|m| m.get("transaction_id").and_then(|v| v.as_str()).map(|s| s.to_string()))
-        .filter(|id| !id.is_empty())
-        .collect();
-    
-    let mut transactions_data: Vec<serde_json::Value> = Vec::new();
+    // Get transactions for this shift, including older sales linked only through cash movements
+    let transactions_data: Vec<serde_json::Value> = conn.prepare(
+        "SELECT t.id, t.total, t.timestamp, t.payment_method, t.receipt_number, t.rounding_amount, t.voided_at IS NOT NULL,
+                (SELECT GROUP_CONCAT(ti.name || ' x' || printf('%g', ti.quantity), ', ') FROM transaction_items ti WHERE ti.transaction_id = t.id) as items
+         FROM transactions t
+         WHERE t.shift_id = ?1
+            OR t.id IN (SELECT transaction_id FROM cash_movements WHERE shift_id = ?1 AND transaction_id IS NOT NULL)
+         ORDER BY t.timestamp DESC"
+    ).map_err(|e| e.to_string())?
+    .query_map(params![shift_id], |row| {
+        Ok(serde_json::json!({
+            "id": row.get::<_, String>(0)?,
+            "total": row.get::<_, f64>(1)?,
+            "timestamp": row.get::<_, String>(2)?,
+            "payment_method": row.get::<_, String>(3)?,
+            "receipt_number": row.get::<_, Option<String>>(4)?,
+            "rounding_amount": row.get::<_, f64>(5)?,
+            "voided": row.get::<_, bool>(6)?,
+            "items": row.get::<_, Option<String>>(7)?,
+        }))
+    }).map_err(|e| e.to_string())?
+    .collect::<Result<_, _>>()
+    .map_err(|e| e.to_string())?;
     
-    for tx_id in &transaction_ids {
-        match conn.query_row(
-            "SELECT t.id, t.total, t.timestamp, t.payment_method, GROUP_CONCAT(ti.name || ' x' || ti.quantity, ', ') as items
+    // Cash rounding is part of the sale totals; report it so the drawer reconciles
+    let rounding_total: f64 = transactions_data.iter()
+        .filter(|t| !t["voided"].as_bool().unwrap_or(false))
+        .map(|t| t["rounding_amount"].as_f64().unwrap_or(0.0))
+        .sum();
+    cash_summary["rounding_total"] = serde_json::json!(pricing::round_money(rounding_total));
+    
+    // Sales per tender type; sales recorded before tender lines existed count
+    // under their transaction's payment method
+    let tender_summary: Vec<serde_json::Value> = conn.prepare(
+        "SELECT tenders.method, COALESCE(pm.display_name, tenders.method), COUNT(DISTINCT tenders.transaction_id),
+                SUM(tenders.amount), SUM(tenders.change_given)
+         FROM (
+             SELECT p.transaction_id, p.method, p.amount, p.change_given
+             FROM payments p
+             JOIN transactions t ON t.id = p.transaction_id
+             WHERE t.shift_id = ?1 AND t.voided_at IS NULL
+             UNION ALL
+             SELECT t.id, t.payment_method, t.total, 0
              FROM transactions t
-             LEFT JOIN transaction_items ti ON t.id = ti.transaction_id
-             WHERE t.id = ?1
-             GROUP BY t.id",
-            params![tx_id.as_str()],
-            |row| {
-                Ok(serde_json::json!({
-                    "id": row.get::<_, String>(0)?,
-                    "total": row.get::<_, f64>(1)?,
-                    "timestamp": row.get::<_, String>(2)?,
-                    "payment_method": row.get::<_, String>(3)?,
-                    "items": row.get::<_, Option<String>>(4)?,
-                }))
-            }
-        ) {
-            Ok(t) => transactions_data.push(t),
-            Err(rusqlite::Error::QueryReturnedNoRows) => {},
-            Err(e) => return Err(e.to_string()),
-        }
-    }
+             WHERE t.shift_id = ?1 AND t.voided_at IS NULL AND NOT EXISTS (SELECT 1 FROM payments p WHERE p.transaction_id = t.id)
+         ) tenders
+         LEFT JOIN payment_methods pm ON pm.code = tenders.method
+         GROUP BY tenders.method
+         ORDER BY COALESCE(pm.sort_order, 999), tenders.method"
+    ).map_err(|e| e.to_string())?
+    .query_map(params![shift_id], |row| {
+        Ok(serde_json::json!({
+            "method": row.get::<_, String>(0)?,
+            "display_name": row.get::<_, String>(1)?,
+            "transaction_count": row.get::<_, i64>(2)?,
+            "amount": pricing::round_money(row.get::<_, f64>(3)?),
+            "change_given": pricing::round_money(row.get::<_, f64>(4)?),
+        }))
+    }).map_err(|e| e.to_string())?
+    .collect::<Result<_, _>>()
+    .map_err(|e| e.to_string())?;
+    
+    // Tax per class and rate for sales made during the shift
+    let tax_summary: Vec<serde_json::Value> = conn.prepare(
+        "SELECT COALESCE(ti.tax_class, 'exempt'), ti.tax_rate, SUM(ti.taxable_amount), SUM(ti.tax_amount)
+         FROM transaction_items ti
+         JOIN transactions t ON t.id = ti.transaction_id
+         WHERE t.shift_id = ?1 AND t.voided_at IS NULL
+         GROUP BY 1, 2
+         ORDER BY 2 DESC"
+    ).map_err(|e| e.to_string())?
+    .query_map(params![shift_id], |row| {
+        Ok(serde_json::json!({
+            "tax_class": row.get::<_, String>(0)?,
+            "tax_rate": row.get::<_, f64>(1)?,
+            "taxable_amount": pricing::round_money(row.get::<_, f64>(2)?),
+            "tax_amount": pricing::round_money(row.get::<_, f64>(3)?),
+        }))
+    }).map_err(|e| e.to_string())?
+    .collect::<Result<_, _>>()
+    .map_err(|e| e.to_string())?;
     
-    let report_data = ShiftReportData {
+    Ok(ShiftReportData {
         shift_info,
         cash_summary,
         movements,
         transactions: transactions_data,
+        tax_summary,
+        tender_summary,
+    })
+}
+
+fn insert_shift_report(conn: &Connection, report: &ShiftReport) -> Result<(), String> {
+    let data_json = serde_json::to_string(&report.data).map_err(|e| e.to_string())?;
+    
+    conn.execute(
+        "INSERT INTO shift_reports (id, shift_id, report_type, data, pdf_path, generated_at, generated_by, report_number) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![report.id, report.shift_id, report.report_type, data_json, report.pdf_path, report.generated_at, report.generated_by, report.report_number]
+    ).map_err(|e| e.to_string())?;
+    
+    Ok(())
+}
+
+fn find_shift_report(conn: &Connection, sql: &str, key: &str) -> Result<Option<ShiftReport>, String> {
+    match conn.query_row(sql, params![key], |row| {
+        Ok(ShiftReport {
+            id: row.get(0)?,
+            shift_id: row.get(1)?,
+            report_type: row.get(2)?,
+            data: serde_json::from_str(row.get::<_, String>(3)?.as_str()).unwrap_or_default(),
+            pdf_path: row.get(4)?,
+            generated_at: row.get(5)?,
+            generated_by: row.get(6)?,
+            report_number: row.get(7)?,
+        })
+    }) {
+        Ok(report) => Ok(Some(report)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// Look up a printer by id, or the default printer of the given type
+fn load_printer_settings(conn: &Connection, printer_id: Option<&str>, printer_type: &str) -> Result<PrinterSettings, String> {
+    let map_printer = |row: &rusqlite::Row| {
+        Ok(PrinterSettings {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            printer_type: row.get(2)?,
+            connection_type: row.get(3)?,
+            config: serde_json::from_str(row.get::<_, String>(4)?.as_str()).unwrap_or_default(),
+            is_default: row.get(5)?,
+        })
+    };
+    
+    let result = match printer_id {
+        Some(id) => conn.query_row(
+            "SELECT id, name, type, connection_type, config, is_default FROM printer_settings WHERE id = ?1",
+            params![id],
+            map_printer
+        ),
+        None => conn.query_row(
+            "SELECT id, name, type, connection_type, config, is_default FROM printer_settings WHERE type = ?1 ORDER BY is_default DESC LIMIT 1",
+            params![printer_type],
+            map_printer
+        ),
     };
     
+    match result {
+        Ok(printer) => Ok(printer),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Err(format!("No {} printer configured", printer_type)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[tauri::command]
+fn generate_shift_report(shift_id: String, user_id: String, db: State<Database>) -> Result<ShiftReport, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    
+    let report_data = build_shift_report_data(&conn, &shift_id)?;
+    
     let report = ShiftReport {
         id: Uuid::new_v4().to_string(),
         shift_id,
@@ -911,29 +2333,106 @@ fn generate_shift_report(shift_id: String, user_id: String, db: State<Database>)
         pdf_path: None,
         generated_at: Utc::now().to_rfc3339(),
         generated_by: user_id,
+        report_number: None,
     };
     
     Ok(report)
 }
 
+// X-report: mid-shift snapshot; does not close or reset anything
 #[tauri::command]
-fn save_shift_report(report: ShiftReport, db: State<Database>) -> Result<String, String> {
+fn generate_x_report(shift_id: String, user_id: String, db: State<Database>) -> Result<ShiftReport, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
     
-    let data_json = serde_json::to_string(&report.data).map_err(|e| e.to_string())?;
+    let report_data = build_shift_report_data(&conn, &shift_id)?;
     
-    conn.execute(
-        "INSERT INTO shift_reports (id, shift_id, report_type, data, pdf_path, generated_at, generated_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-        params![report.id, report.shift_id, report.report_type, data_json, report.pdf_path, report.generated_at, report.generated_by]
+    let report = ShiftReport {
+        id: Uuid::new_v4().to_string(),
+        shift_id,
+        report_type: "x".to_string(),
+        data: serde_json::to_value(report_data).map_err(|e| e.to_string())?,
+        pdf_path: None,
+        generated_at: Utc::now().to_rfc3339(),
+        generated_by: user_id,
+        report_number: None,
+    };
+    
+    insert_shift_report(&conn, &report)?;
+    
+    Ok(report)
+}
+
+// Z-report: closing report for a closed shift. The report number is assigned
+// once; asking again for the same shift returns the stored report unchanged.
+#[tauri::command]
+fn generate_z_report(shift_id: String, user_id: String, db: State<Database>) -> Result<ShiftReport, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    
+    let existing = find_shift_report(
+        &tx,
+        "SELECT id, shift_id, report_type, data, pdf_path, generated_at, generated_by, report_number FROM shift_reports WHERE shift_id = ?1 AND report_type = 'z'",
+        &shift_id
+    )?;
+    
+    if let Some(report) = existing {
+        return Ok(report);
+    }
+    
+    let status: String = tx.query_row(
+        "SELECT status FROM cash_shifts WHERE id = ?1",
+        params![shift_id],
+        |row| row.get(0)
+    ).map_err(|e| e.to_string())?;
+    
+    if status != "closed" {
+        return Err("Shift must be closed before a Z-report can be generated".to_string());
+    }
+    
+    let report_number: i64 = tx.query_row(
+        "SELECT COALESCE(MAX(report_number), 0) + 1 FROM shift_reports WHERE report_type = 'z'",
+        [],
+        |row| row.get(0)
     ).map_err(|e| e.to_string())?;
     
+    let report_data = build_shift_report_data(&tx, &shift_id)?;
+    
+    let report = ShiftReport {
+        id: Uuid::new_v4().to_string(),
+        shift_id,
+        report_type: "z".to_string(),
+        data: serde_json::to_value(report_data).map_err(|e| e.to_string())?,
+        pdf_path: None,
+        generated_at: Utc::now().to_rfc3339(),
+        generated_by: user_id,
+        report_number: Some(report_number),
+    };
+    
+    insert_shift_report(&tx, &report)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    
+    Ok(report)
+}
+
+#[tauri::command]
+fn save_shift_report(report: ShiftReport, db: State<Database>) -> Result<String, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    
+    // Z-reports carry a sequential number and are only created by generate_z_report
+    if report.report_type == "z" {
+        return Err("Z-reports can only be created with generate_z_report".to_string());
+    }
+    
+    let report = ShiftReport { report_number: None, ..report };
+    insert_shift_report(&conn, &report)?;
+    
     Ok(report.id)
 }
 
 #[tauri::command]
 fn get_shift_reports(db: State<Database>) -> Result<Vec<ShiftReport>, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
-    let mut stmt = conn.prepare("SELECT id, shift_id, report_type, data, pdf_path, generated_at, generated_by FROM shift_reports ORDER BY generated_at DESC").map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare("SELECT id, shift_id, report_type, data, pdf_path, generated_at, generated_by, report_number FROM shift_reports ORDER BY generated_at DESC").map_err(|e| e.to_string())?;
     
     let report_iter = stmt.query_map([], |row| {
         Ok(ShiftReport {
@@ -944,6 +2443,7 @@ fn get_shift_reports(db: State<Database>) -> Result<Vec<ShiftReport>, String> {
             pdf_path: row.get(4)?,
             generated_at: row.get(5)?,
             generated_by: row.get(6)?,
+            report_number: row.get(7)?,
         })
     }).map_err(|e| e.to_string())?;
     
@@ -955,6 +2455,23 @@ fn get_shift_reports(db: State<Database>) -> Result<Vec<ShiftReport>, String> {
     Ok(reports)
 }
 
+// Print a saved shift report on the receipt printer (58 mm by default)
+#[tauri::command]
+fn print_shift_report(report_id: String, printer_id: Option<String>, db: State<Database>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    
+    let report = find_shift_report(
+        &conn,
+        "SELECT id, shift_id, report_type, data, pdf_path, generated_at, generated_by, report_number FROM shift_reports WHERE id = ?1",
+        &report_id
+    )?.ok_or_else(|| "Shift report not found".to_string())?;
+    
+    let printer = load_printer_settings(&conn, printer_id.as_deref(), "receipt")?;
+    let doc = printing::render_shift_report(&report, printing::line_width(&printer.config));
+    
+    printing::print_document(&printer, &doc)
+}
+
 #[tauri::command]
 fn close_cash_shift(shift_id: String, actual_cash: f64, user_id: String, notes: Option<String>, db: State<Database>) -> Result<String, String> {
     let conn = db.0.lock().map_err(|e| e.to_string())?;
@@ -994,8 +2511,11 @@ fn main() {
             get_product_by_barcode,
             create_transaction,
             get_transactions,
+            search_transactions,
             update_product_stock,
             print_receipt,
+            get_transaction_by_receipt_number,
+            reprint_receipt,
             // New cash management commands
             authenticate_user,
             get_users,
@@ -1005,11 +2525,118 @@ fn main() {
             get_cash_movements,
             get_receipt_templates,
             get_printer_settings,
+            get_app_settings,
+            update_app_setting,
             // Shift report commands
             generate_shift_report,
             save_shift_report,
             get_shift_reports,
-            close_cash_shift
+            close_cash_shift,
+            generate_x_report,
+            generate_z_report,
+            print_shift_report,
+            // Sales analytics commands
+            analytics::get_sales_by_hour,
+            analytics::get_sales_by_period,
+            analytics::get_sales_by_cashier,
+            analytics::get_sales_by_category,
+            analytics::get_top_products,
+            // Promotion commands
+            promotions::price_cart,
+            promotions::get_promotions,
+            promotions::create_promotion,
+            promotions::update_promotion,
+            promotions::delete_promotion,
+            // Tax commands
+            tax::get_tax_classes,
+            tax::save_tax_class,
+            tax::set_product_tax_class,
+            // Payment method commands
+            payments::get_payment_methods,
+            payments::save_payment_method,
+            payments::set_payment_method_active,
+            analytics::get_sales_by_payment_method,
+            analytics::get_margin_report,
+            // Parked cart commands
+            carts::park_cart,
+            carts::list_parked_carts,
+            carts::resume_cart,
+            carts::delete_parked_cart,
+            // Customer commands
+            customers::get_customers,
+            customers::get_customer,
+            customers::search_customers_by_phone,
+            customers::create_customer,
+            customers::update_customer,
+            customers::delete_customer,
+            customers::get_customer_purchase_history,
+            void_transaction,
+            // Gift card and store credit commands
+            giftcards::sell_gift_card,
+            giftcards::get_gift_card,
+            giftcards::get_gift_card_history,
+            giftcards::deactivate_gift_card,
+            // Accounts receivable commands
+            receivables::set_customer_credit_limit,
+            receivables::get_customer_account,
+            receivables::receive_account_payment,
+            receivables::get_ar_aging,
+            receivables::get_customer_statement,
+            // Inventory commands
+            inventory::record_stock_movement,
+            inventory::get_stock_card,
+            inventory::verify_stock,
+            // Supplier and purchase order commands
+            purchasing::get_suppliers,
+            purchasing::create_supplier,
+            purchasing::update_supplier,
+            purchasing::delete_supplier,
+            purchasing::create_purchase_order,
+            purchasing::get_purchase_order,
+            purchasing::list_purchase_orders,
+            purchasing::receive_purchase_order,
+            purchasing::close_purchase_order,
+            purchasing::get_outstanding_purchase_orders,
+            purchasing::set_product_cost,
+            // Alternate barcode commands
+            barcodes::get_product_barcodes,
+            barcodes::add_product_barcode,
+            barcodes::remove_product_barcode,
+            barcodes::validate_barcode,
+            barcodes::generate_internal_barcode,
+            barcodes::assign_internal_barcode,
+            // Shelf label commands
+            labels::set_product_content,
+            labels::get_shelf_labels,
+            labels::print_shelf_labels,
+            labels::export_shelf_labels_pdf,
+            // Weighed product commands
+            scale::set_product_scale,
+            scale::get_scale_label_layouts,
+            // Product variant commands
+            variants::create_parent_product,
+            variants::update_parent_product,
+            variants::add_variant,
+            variants::set_variant_price,
+            variants::get_parent_products,
+            variants::get_parent_product,
+            analytics::get_sales_by_parent_product,
+            // Reorder commands
+            reorder::set_product_reorder,
+            reorder::get_reorder_suggestions,
+            // Stocktake commands
+            stocktake::start_stocktake,
+            stocktake::list_stocktakes,
+            stocktake::record_stocktake_count,
+            stocktake::get_stocktake,
+            stocktake::approve_stocktake,
+            stocktake::cancel_stocktake,
+            // Loyalty commands
+            loyalty::get_loyalty_balance,
+            loyalty::get_loyalty_ledger,
+            loyalty::adjust_loyalty_points,
+            loyalty::get_loyalty_multipliers,
+            loyalty::set_loyalty_multiplier
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
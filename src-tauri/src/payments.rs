@@ -0,0 +1,326 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::pricing::round_money;
+use crate::Database;
+
+// Payment method from the `payment_methods` registry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentMethod {
+    pub code: String,
+    pub display_name: String,
+    // Counts towards the shift's expected drawer cash
+    pub is_drawer_cash: bool,
+    // Kicks the cash drawer when the receipt prints
+    pub opens_drawer: bool,
+    // May be over-tendered, with the excess returned as change
+    pub allows_change: bool,
+    // A reference (e.g. card approval code) must be entered
+    pub requires_reference: bool,
+    pub is_active: bool,
+    #[serde(default)]
+    pub sort_order: i64,
+}
+
+// A tender offered by the customer; `amount` is what was handed over
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaymentInput {
+    pub method: String,
+    pub amount: f64,
+    pub reference: Option<String>,
+}
+
+// A tender line stored in the `payments` table. `amount` is the part of the
+// sale it pays for; `tendered` - `change_given` = `amount`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payment {
+    pub method: String,
+    pub amount: f64,
+    pub tendered: f64,
+    pub change_given: f64,
+    pub reference: Option<String>,
+}
+
+fn find_method<'a>(methods: &'a [PaymentMethod], code: &str) -> Result<&'a PaymentMethod, String> {
+    methods
+        .iter()
+        .find(|m| m.code == code && m.is_active)
+        .ok_or_else(|| format!("Unknown or inactive payment method: {}", code))
+}
+
+// Rounding applied to the part of a sale paid in drawer cash, from the
+// `cash_rounding_increment` (0 disables it) and `cash_rounding_mode`
+// ("nearest", "up" or "down") settings
+#[derive(Debug, Clone)]
+pub struct CashRounding {
+    pub increment: f64,
+    pub mode: String,
+}
+
+// Tender lines for a sale plus the cash rounding difference; the payments add
+// up to the sale total plus `rounding_amount`
+#[derive(Debug)]
+pub struct TenderAllocation {
+    pub payments: Vec<Payment>,
+    pub rounding_amount: f64,
+}
+
+pub fn cash_rounding(conn: &Connection) -> Result<CashRounding, String> {
+    let increment = crate::setting_f64(conn, "cash_rounding_increment", 0.0)?;
+    let mode = crate::get_setting(conn, "cash_rounding_mode")?
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_else(|| "nearest".to_string());
+
+    Ok(CashRounding { increment, mode })
+}
+
+// Round an amount due in cash to the configured increment
+pub fn round_cash(amount: f64, rounding: &CashRounding) -> f64 {
+    // Work in whole cents so that exact multiples are not pushed to the next
+    // step; an increment below half a cent rounds to nothing and disables it
+    let increment_cents = (rounding.increment * 100.0).round();
+    if !increment_cents.is_finite() || increment_cents <= 0.0 {
+        return round_money(amount);
+    }
+
+    let units = round_money(amount) * 100.0 / increment_cents;
+    let steps = match rounding.mode.as_str() {
+        "up" => (units - 1e-9).ceil(),
+        "down" => (units + 1e-9).floor(),
+        _ => units.round(),
+    };
+
+    round_money(steps * increment_cents / 100.0)
+}
+
+// Whether the tender that takes change for a sale is drawer cash, i.e. whether
+// cash rounding applies to what remains after exact tenders
+fn rounds_cash(tenders: &[PaymentInput], methods: &[PaymentMethod]) -> bool {
+    tenders
+        .iter()
+        .filter_map(|t| methods.iter().find(|m| m.code == t.method))
+        .rfind(|m| m.allows_change)
+        .map(|m| m.is_drawer_cash)
+        .unwrap_or(false)
+}
+
+// Amount due when the whole sale is paid with one method
+pub fn amount_due(total: f64, method: &str, methods: &[PaymentMethod], rounding: &CashRounding) -> f64 {
+    match methods.iter().find(|m| m.code == method) {
+        Some(m) if m.allows_change && m.is_drawer_cash => round_cash(total, rounding),
+        _ => total,
+    }
+}
+
+// Apply tenders to a sale total. Tenders that do not allow change are applied
+// in full and may not exceed what is due; change-giving tenders (cash) cover
+// the rest and any excess is given back as change on the last of them. When
+// that rest is paid in drawer cash it is rounded with the cash rounding rule.
+pub fn allocate_tenders(total: f64, tenders: &[PaymentInput], methods: &[PaymentMethod], rounding: &CashRounding) -> Result<TenderAllocation, String> {
+    if tenders.is_empty() {
+        return Err("At least one payment is required".to_string());
+    }
+
+    let mut gives_change = Vec::with_capacity(tenders.len());
+    for tender in tenders {
+        let method = find_method(methods, &tender.method)?;
+        if tender.amount <= 0.0 || !tender.amount.is_finite() {
+            return Err(format!("Invalid amount for {} payment", method.display_name));
+        }
+        if method.requires_reference && tender.reference.as_deref().map(str::trim).unwrap_or("").is_empty() {
+            return Err(format!("{} payments require a reference", method.display_name));
+        }
+        gives_change.push(method.allows_change);
+    }
+
+    let exact: f64 = tenders.iter().zip(&gives_change).filter(|(_, change)| !**change).map(|(t, _)| t.amount).sum();
+    if round_money(exact - total) > 0.0 {
+        return Err("Payments that cannot give change exceed the amount due".to_string());
+    }
+
+    let mut change_due = round_money(total - exact);
+    let mut rounding_amount = 0.0;
+    if rounds_cash(tenders, methods) {
+        let rounded = round_cash(change_due, rounding);
+        rounding_amount = round_money(rounded - change_due);
+        change_due = rounded;
+    }
+
+    let due = round_money(total + rounding_amount);
+    let tendered: f64 = tenders.iter().map(|t| t.amount).sum();
+    if round_money(tendered - due) < 0.0 {
+        return Err(format!("Payments of {:.2} do not cover the total of {:.2}", tendered, due));
+    }
+
+    let last_change = gives_change.iter().rposition(|change| *change);
+
+    let payments = tenders
+        .iter()
+        .enumerate()
+        .map(|(index, tender)| {
+            let amount = if !gives_change[index] {
+                round_money(tender.amount)
+            } else if Some(index) == last_change {
+                change_due
+            } else {
+                round_money(tender.amount.min(change_due))
+            };
+            if gives_change[index] {
+                change_due = round_money(change_due - amount);
+            }
+            Payment {
+                method: tender.method.clone(),
+                amount,
+                tendered: round_money(tender.amount),
+                change_given: round_money(tender.amount - amount),
+                reference: tender.reference.clone(),
+            }
+        })
+        .collect();
+
+    Ok(TenderAllocation { payments, rounding_amount })
+}
+
+// Store tender lines and post drawer cash to the shift's cash ledger
+pub fn record_payments(
+    conn: &Connection,
+    transaction_id: &str,
+    shift_id: Option<&str>,
+    user_id: Option<&str>,
+    payments: &[Payment],
+    methods: &[PaymentMethod],
+    timestamp: &str,
+) -> Result<(), String> {
+    for payment in payments {
+        conn.execute(
+            "INSERT INTO payments (id, transaction_id, method, amount, tendered, change_given, reference, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![Uuid::new_v4().to_string(), transaction_id, payment.method, payment.amount, payment.tendered, payment.change_given, payment.reference, timestamp]
+        ).map_err(|e| e.to_string())?;
+    }
+
+    let cash: f64 = round_money(
+        payments
+            .iter()
+            .filter(|p| methods.iter().any(|m| m.code == p.method && m.is_drawer_cash))
+            .map(|p| p.amount)
+            .sum(),
+    );
+
+    if let (Some(shift_id), Some(user_id)) = (shift_id, user_id) {
+        if cash > 0.0 {
+            conn.execute(
+                "INSERT INTO cash_movements (id, shift_id, transaction_id, movement_type, amount, reason, timestamp, user_id) VALUES (?1, ?2, ?3, 'sale', ?4, ?5, ?6, ?7)",
+                params![Uuid::new_v4().to_string(), shift_id, transaction_id, cash, "Cash sale", timestamp, user_id]
+            ).map_err(|e| e.to_string())?;
+
+            conn.execute(
+                "UPDATE cash_shifts SET expected_cash = expected_cash + ?1 WHERE id = ?2",
+                params![cash, shift_id]
+            ).map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(())
+}
+
+// Tender lines for a set of transactions, keyed by transaction id
+pub fn load_payments(conn: &Connection, sql: &str, values: Vec<rusqlite::types::Value>) -> Result<Vec<(String, Payment)>, String> {
+    let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+
+    let payment_iter = stmt.query_map(rusqlite::params_from_iter(values), |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            Payment {
+                method: row.get(1)?,
+                amount: row.get(2)?,
+                tendered: row.get(3)?,
+                change_given: row.get(4)?,
+                reference: row.get(5)?,
+            },
+        ))
+    }).map_err(|e| e.to_string())?;
+
+    let mut payments = Vec::new();
+    for payment in payment_iter {
+        payments.push(payment.map_err(|e| e.to_string())?);
+    }
+
+    Ok(payments)
+}
+
+fn map_payment_method(row: &rusqlite::Row) -> rusqlite::Result<PaymentMethod> {
+    Ok(PaymentMethod {
+        code: row.get(0)?,
+        display_name: row.get(1)?,
+        is_drawer_cash: row.get(2)?,
+        opens_drawer: row.get(3)?,
+        allows_change: row.get(4)?,
+        requires_reference: row.get(5)?,
+        is_active: row.get(6)?,
+        sort_order: row.get(7)?,
+    })
+}
+
+pub fn load_payment_methods(conn: &Connection) -> Result<Vec<PaymentMethod>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT code, display_name, is_drawer_cash, opens_drawer, allows_change, requires_reference, is_active, sort_order
+         FROM payment_methods
+         ORDER BY sort_order, code"
+    ).map_err(|e| e.to_string())?;
+
+    let method_iter = stmt.query_map([], map_payment_method).map_err(|e| e.to_string())?;
+
+    let mut methods = Vec::new();
+    for method in method_iter {
+        methods.push(method.map_err(|e| e.to_string())?);
+    }
+
+    Ok(methods)
+}
+
+#[tauri::command]
+pub fn get_payment_methods(include_inactive: Option<bool>, db: State<Database>) -> Result<Vec<PaymentMethod>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let methods = load_payment_methods(&conn)?;
+
+    if include_inactive.unwrap_or(false) {
+        Ok(methods)
+    } else {
+        Ok(methods.into_iter().filter(|m| m.is_active).collect())
+    }
+}
+
+// Create or update a payment method by code
+#[tauri::command]
+pub fn save_payment_method(method: PaymentMethod, db: State<Database>) -> Result<(), String> {
+    let code = method.code.trim().to_lowercase();
+    if code.is_empty() || code == "split" {
+        return Err("Invalid payment method code".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "INSERT INTO payment_methods (code, display_name, is_drawer_cash, opens_drawer, allows_change, requires_reference, is_active, sort_order)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+         ON CONFLICT(code) DO UPDATE SET display_name = excluded.display_name, is_drawer_cash = excluded.is_drawer_cash,
+             opens_drawer = excluded.opens_drawer, allows_change = excluded.allows_change,
+             requires_reference = excluded.requires_reference, is_active = excluded.is_active, sort_order = excluded.sort_order",
+        params![code, method.display_name, method.is_drawer_cash, method.opens_drawer, method.allows_change, method.requires_reference, method.is_active, method.sort_order]
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_payment_method_active(code: String, is_active: bool, db: State<Database>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "UPDATE payment_methods SET is_active = ?1 WHERE code = ?2",
+        params![is_active, code]
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
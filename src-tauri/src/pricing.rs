@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+use crate::LoginData;
+
+// A discount requested by the cashier, either on one line or on the whole cart.
+// `discount_type` is "percentage" (value 0-100) or "fixed" (currency amount).
+// Discounts above the approval threshold must carry a manager's credentials.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiscountInput {
+    pub discount_type: String,
+    pub value: f64,
+    pub reason: Option<String>,
+    #[serde(skip_serializing)]
+    pub approval: Option<LoginData>,
+}
+
+// Round a currency amount to two decimals, halves away from zero
+pub fn round_money(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+// Discount amount for a gross amount, never more than the amount itself
+pub fn discount_amount(gross: f64, discount: &DiscountInput) -> Result<f64, String> {
+    if discount.value < 0.0 || !discount.value.is_finite() {
+        return Err("Discount value must be a positive number".to_string());
+    }
+
+    let amount = match discount.discount_type.as_str() {
+        "percentage" => {
+            if discount.value > 100.0 {
+                return Err("Percentage discount cannot exceed 100%".to_string());
+            }
+            gross * discount.value / 100.0
+        }
+        "fixed" => discount.value,
+        other => return Err(format!("Unknown discount type: {}", other)),
+    };
+
+    Ok(round_money(amount.min(gross)))
+}
+
+// Effective discount as a percentage of the gross amount
+pub fn discount_percent(gross: f64, amount: f64) -> f64 {
+    if gross <= 0.0 {
+        0.0
+    } else {
+        amount / gross * 100.0
+    }
+}
@@ -0,0 +1,329 @@
+use chrono::{DateTime, Local};
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use crate::{PrinterSettings, ShiftReport, Transaction};
+
+// ESC/POS control sequences
+const ESC_INIT: &[u8] = &[0x1B, 0x40];
+const GS_CUT_PARTIAL: &[u8] = &[0x1D, 0x56, 0x42, 0x00];
+// Pulse drawer kick pin 2 (on 50ms, off 500ms)
+const ESC_DRAWER_KICK: &[u8] = &[0x1B, 0x70, 0x00, 0x19, 0xFA];
+
+// Plain text document laid out for a fixed character width
+pub struct TextDocument {
+    pub width: usize,
+    pub lines: Vec<String>,
+    // Open the cash drawer after printing
+    pub open_drawer: bool,
+}
+
+impl TextDocument {
+    pub fn new(width: usize) -> Self {
+        TextDocument { width, lines: Vec::new(), open_drawer: false }
+    }
+
+    pub fn line(&mut self, text: &str) {
+        // Wrap long text so the printer does not break words mid-line on its own
+        let chars: Vec<char> = text.chars().collect();
+        if chars.is_empty() {
+            self.lines.push(String::new());
+            return;
+        }
+        for chunk in chars.chunks(self.width) {
+            self.lines.push(chunk.iter().collect());
+        }
+    }
+
+    pub fn center(&mut self, text: &str) {
+        let len = text.chars().count();
+        if len >= self.width {
+            self.line(text);
+        } else {
+            let pad = (self.width - len) / 2;
+            self.lines.push(format!("{}{}", " ".repeat(pad), text));
+        }
+    }
+
+    // Label on the left, value right-aligned on the same line
+    pub fn pair(&mut self, label: &str, value: &str) {
+        let label_len = label.chars().count();
+        let value_len = value.chars().count();
+        if label_len + value_len < self.width {
+            let gap = self.width - label_len - value_len;
+            self.lines.push(format!("{}{}{}", label, " ".repeat(gap), value));
+        } else {
+            self.line(label);
+            self.lines.push(format!("{:>width$}", value, width = self.width));
+        }
+    }
+
+    pub fn separator(&mut self) {
+        self.lines.push("-".repeat(self.width));
+    }
+
+    pub fn double_separator(&mut self) {
+        self.lines.push("=".repeat(self.width));
+    }
+
+    pub fn blank(&mut self) {
+        self.lines.push(String::new());
+    }
+}
+
+// Characters per line for the configured paper width (font A)
+pub fn line_width(config: &serde_json::Value) -> usize {
+    if let Some(chars) = config["chars_per_line"].as_u64() {
+        return chars as usize;
+    }
+    match config["paper_width"].as_u64() {
+        Some(width) if width >= 80 => 48,
+        _ => 32,
+    }
+}
+
+pub fn format_amount(amount: f64) -> String {
+    // Adding 0.0 turns -0.0 into 0.0 so empty sums do not print as "-0.00"
+    format!("{:.2}", amount + 0.0)
+}
+
+// Tax rate fraction as a percentage, e.g. 0.11 -> "11"
+pub fn format_percent(rate: f64) -> String {
+    format!("{}", (rate * 10000.0).round() / 100.0)
+}
+
+// Stored timestamps are RFC 3339 in UTC; print them in local time
+pub fn format_timestamp(timestamp: &str) -> String {
+    match DateTime::parse_from_rfc3339(timestamp) {
+        Ok(time) => time.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string(),
+        Err(_) => timestamp.to_string(),
+    }
+}
+
+// Encode a document as ESC/POS bytes; non-ASCII characters are replaced
+// because the printer runs a single-byte code page
+pub fn encode_escpos(doc: &TextDocument, auto_cut: bool) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(ESC_INIT);
+    for line in &doc.lines {
+        for c in line.chars() {
+            bytes.push(if c.is_ascii() { c as u8 } else { b'?' });
+        }
+        bytes.push(b'\n');
+    }
+    // Feed past the tear bar before cutting
+    bytes.extend_from_slice(b"\n\n\n");
+    if auto_cut {
+        bytes.extend_from_slice(GS_CUT_PARTIAL);
+    }
+    if doc.open_drawer {
+        bytes.extend_from_slice(ESC_DRAWER_KICK);
+    }
+    bytes
+}
+
+// Send a document to the printer. Printers configured with a `device_path`
+// (e.g. /dev/usb/lp0) receive raw ESC/POS bytes; otherwise the document is
+// written to stdout as the receipt placeholder does.
+pub fn print_document(printer: &PrinterSettings, doc: &TextDocument) -> Result<(), String> {
+    let auto_cut = printer.config["auto_cut"].as_bool().unwrap_or(false);
+
+    match printer.config["device_path"].as_str() {
+        Some(path) => write_device(path, &encode_escpos(doc, auto_cut)),
+        None => {
+            for line in &doc.lines {
+                println!("{}", line);
+            }
+            Ok(())
+        }
+    }
+}
+
+// Send raw printer commands (e.g. TSPL or ZPL) as they are; without a
+// `device_path` they are written to stdout
+pub fn print_raw(printer: &PrinterSettings, bytes: &[u8]) -> Result<(), String> {
+    match printer.config["device_path"].as_str() {
+        Some(path) => write_device(path, bytes),
+        None => {
+            print!("{}", String::from_utf8_lossy(bytes));
+            Ok(())
+        }
+    }
+}
+
+fn write_device(path: &str, bytes: &[u8]) -> Result<(), String> {
+    let mut device = OpenOptions::new()
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open printer {}: {}", path, e))?;
+    device.write_all(bytes).map_err(|e| e.to_string())?;
+    device.flush().map_err(|e| e.to_string())
+}
+
+// Render an X, Z or daily shift report for a receipt printer
+pub fn render_shift_report(report: &ShiftReport, width: usize) -> TextDocument {
+    let mut doc = TextDocument::new(width);
+    let shift = &report.data["shift_info"];
+    let summary = &report.data["cash_summary"];
+
+    let title = match report.report_type.as_str() {
+        "x" => "X-REPORT".to_string(),
+        "z" => match report.report_number {
+            Some(number) => format!("Z-REPORT #{:06}", number),
+            None => "Z-REPORT".to_string(),
+        },
+        _ => "SHIFT REPORT".to_string(),
+    };
+
+    doc.center(&title);
+    if report.report_type == "x" {
+        doc.center("(NOT A CLOSING REPORT)");
+    }
+    doc.double_separator();
+    doc.pair("Register", shift["register_name"].as_str().unwrap_or("-"));
+    doc.pair("Cashier", shift["user_name"].as_str().unwrap_or("-"));
+    doc.pair("Opened", &format_timestamp(shift["start_time"].as_str().unwrap_or("-")));
+    if let Some(end_time) = shift["end_time"].as_str() {
+        doc.pair("Closed", &format_timestamp(end_time));
+    }
+    doc.pair("Printed", &format_timestamp(&report.generated_at));
+    doc.separator();
+
+    doc.pair("Opening cash", &format_amount(summary["initial_cash"].as_f64().unwrap_or(0.0)));
+    doc.pair("Cash in", &format_amount(summary["total_cash_in"].as_f64().unwrap_or(0.0)));
+    doc.pair("Cash out", &format_amount(summary["total_cash_out"].as_f64().unwrap_or(0.0)));
+    doc.pair("Expected cash", &format_amount(summary["expected_cash"].as_f64().unwrap_or(0.0)));
+    if let Some(actual) = summary["actual_cash"].as_f64() {
+        doc.pair("Counted cash", &format_amount(actual));
+    }
+    if let Some(difference) = summary["difference"].as_f64() {
+        doc.pair("Difference", &format_amount(difference));
+    }
+    doc.separator();
+
+    let transactions = report.data["transactions"].as_array().cloned().unwrap_or_default();
+    let (voided, sales): (Vec<_>, Vec<_>) = transactions.iter().partition(|t| t["voided"].as_bool().unwrap_or(false));
+    let sales_total: f64 = sales.iter().map(|t| t["total"].as_f64().unwrap_or(0.0)).sum();
+    doc.pair("Transactions", &sales.len().to_string());
+    doc.pair("Sales total", &format_amount(sales_total));
+    if !voided.is_empty() {
+        let voided_total: f64 = voided.iter().map(|t| t["total"].as_f64().unwrap_or(0.0)).sum();
+        doc.pair(&format!("Voided ({})", voided.len()), &format_amount(voided_total));
+    }
+    if let Some(rounding) = summary["rounding_total"].as_f64().filter(|r| *r != 0.0) {
+        doc.pair("  incl. cash rounding", &format_amount(rounding));
+    }
+
+    if let Some(tender_summary) = report.data["tender_summary"].as_array() {
+        if !tender_summary.is_empty() {
+            doc.separator();
+            doc.line("Tenders");
+            for tender in tender_summary {
+                let name = tender["display_name"].as_str().or(tender["method"].as_str()).unwrap_or("-");
+                let label = format!("  {} ({})", name, tender["transaction_count"].as_i64().unwrap_or(0));
+                doc.pair(&label, &format_amount(tender["amount"].as_f64().unwrap_or(0.0)));
+            }
+        }
+    }
+
+    if let Some(tax_summary) = report.data["tax_summary"].as_array() {
+        if !tax_summary.is_empty() {
+            doc.separator();
+            doc.line("Tax summary");
+            for tax in tax_summary {
+                let label = format!("{} {}%", tax["tax_class"].as_str().unwrap_or("-"), format_percent(tax["tax_rate"].as_f64().unwrap_or(0.0)));
+                doc.pair(&format!("  {} base", label), &format_amount(tax["taxable_amount"].as_f64().unwrap_or(0.0)));
+                doc.pair(&format!("  {} tax", label), &format_amount(tax["tax_amount"].as_f64().unwrap_or(0.0)));
+            }
+        }
+    }
+    doc.double_separator();
+
+    if report.report_type == "z" {
+        doc.center("END OF DAY");
+    }
+    doc.blank();
+    doc
+}
+
+// Render a sales receipt; reprints are marked as copies
+pub fn render_receipt(transaction: &Transaction, store_name: &str, store_address: &str, copy: bool, width: usize) -> TextDocument {
+    let mut doc = TextDocument::new(width);
+
+    doc.center(store_name);
+    doc.center(store_address);
+    if copy {
+        doc.blank();
+        doc.center("*** COPY ***");
+    }
+    doc.separator();
+    doc.pair("Receipt", transaction.receipt_number.as_deref().unwrap_or(&transaction.id));
+    doc.pair("Date", &format_timestamp(&transaction.timestamp));
+    if transaction.voided_at.is_some() {
+        doc.center("*** VOID ***");
+    }
+    doc.separator();
+
+    for item in &transaction.items {
+        doc.line(&item.name);
+        doc.pair(&format!("  {} x {}", item.quantity, format_amount(item.price)), &format_amount(item.subtotal + item.discount_amount));
+        if item.discount_amount > 0.0 {
+            doc.pair("  Discount", &format_amount(-item.discount_amount));
+        }
+    }
+
+    doc.separator();
+    let line_discounts: f64 = transaction.items.iter().map(|i| i.discount_amount).sum();
+    let order_discount = transaction.discount_total - line_discounts;
+    let exclusive = transaction.tax_mode.as_deref() == Some("exclusive");
+    let rounded = transaction.rounding_amount != 0.0;
+    if order_discount > 0.005 || (exclusive && transaction.tax_total > 0.0) || rounded {
+        doc.pair("Subtotal", &format_amount(transaction.subtotal));
+    }
+    if order_discount > 0.005 {
+        doc.pair("Discount", &format_amount(-order_discount));
+    }
+    if exclusive && transaction.tax_total > 0.0 {
+        doc.pair("Tax", &format_amount(transaction.tax_total));
+    }
+    if rounded {
+        doc.pair("Rounding", &format_amount(transaction.rounding_amount));
+    }
+    doc.pair("TOTAL", &format_amount(transaction.total));
+    if transaction.payments.is_empty() {
+        doc.pair("Payment", &transaction.payment_method);
+    }
+    for payment in &transaction.payments {
+        doc.pair(&payment.method.to_uppercase(), &format_amount(payment.tendered));
+        if let Some(reference) = &payment.reference {
+            doc.pair("  Ref", reference);
+        }
+    }
+    let change: f64 = transaction.payments.iter().map(|p| p.change_given).sum();
+    if change > 0.0 {
+        doc.pair("Change", &format_amount(change));
+    }
+    doc.separator();
+
+    // Tax summary per rate, as required on PPN receipts
+    let mut rates: Vec<f64> = Vec::new();
+    for item in &transaction.items {
+        if item.tax_rate > 0.0 && !rates.contains(&item.tax_rate) {
+            rates.push(item.tax_rate);
+        }
+    }
+    for rate in &rates {
+        let tax: f64 = transaction.items.iter().filter(|i| i.tax_rate == *rate).map(|i| i.tax_amount).sum();
+        let label = format!("PPN {}%{}", format_percent(*rate), if exclusive { "" } else { " incl." });
+        doc.pair(&label, &format_amount(tax));
+    }
+    if !rates.is_empty() {
+        doc.separator();
+    }
+    doc.center("Thank you for your business!");
+    if copy {
+        doc.center("*** COPY ***");
+    }
+    doc.blank();
+    doc
+}
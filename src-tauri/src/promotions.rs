@@ -0,0 +1,534 @@
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Local, NaiveDateTime, NaiveTime, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::pricing::{round_money, DiscountInput};
+use crate::{Database, TransactionItem};
+
+// Promotion stored in the `promotions` table. `promo_type` selects how `rules`
+// is interpreted:
+//   buy_x_get_y    buy_quantity, get_quantity, get_discount_percent (default 100)
+//   mix_and_match  quantity, price: any `quantity` matching units for `price`
+//   bundle         components [{product_id, quantity}], price
+//   percent_off    percent
+//   fixed_price    price per unit
+// Every type also accepts product_ids / categories (empty means all products),
+// start_time / end_time ("HH:MM", local), days_of_week (1 = Monday) and member_only.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Promotion {
+    pub id: String,
+    pub name: String,
+    pub promo_type: String,
+    pub rules: serde_json::Value,
+    pub priority: i64,
+    pub stackable: bool,
+    pub is_active: bool,
+    pub starts_at: Option<String>,
+    pub ends_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PromotionRules {
+    pub product_ids: Vec<String>,
+    pub categories: Vec<String>,
+    pub buy_quantity: u32,
+    pub get_quantity: u32,
+    pub get_discount_percent: Option<f64>,
+    pub quantity: u32,
+    pub price: Option<f64>,
+    pub percent: Option<f64>,
+    pub components: Vec<BundleComponent>,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub days_of_week: Vec<u32>,
+    pub member_only: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BundleComponent {
+    pub product_id: String,
+    pub quantity: u32,
+}
+
+// Cart line as seen by the evaluator
+#[derive(Debug, Clone)]
+pub struct CartLine {
+    pub product_id: String,
+    pub category: Option<String>,
+    pub quantity: i32,
+    pub unit_price: f64,
+}
+
+// Discount given by one promotion on one cart line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedPromotion {
+    pub promotion_id: String,
+    pub name: String,
+    pub promo_type: String,
+    pub line_index: usize,
+    pub amount: f64,
+}
+
+// Priced line returned to the frontend by price_cart
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PricedLine {
+    pub product_id: String,
+    pub quantity: f64,
+    pub unit_price: f64,
+    pub gross: f64,
+    pub promotion_discount: f64,
+    pub manual_discount: f64,
+    pub net: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PricedCart {
+    pub lines: Vec<PricedLine>,
+    pub promotions: Vec<AppliedPromotion>,
+    pub gross_total: f64,
+    pub promotion_total: f64,
+    pub discount_total: f64,
+    pub tax_mode: String,
+    pub tax_total: f64,
+    pub total: f64,
+    // Total to collect with `payment_method`, after cash rounding
+    pub amount_due: f64,
+}
+
+// Input for creating or updating a promotion
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PromotionInput {
+    pub name: String,
+    pub promo_type: String,
+    pub rules: serde_json::Value,
+    pub priority: Option<i64>,
+    pub stackable: Option<bool>,
+    pub is_active: Option<bool>,
+    pub starts_at: Option<String>,
+    pub ends_at: Option<String>,
+}
+
+// One unit of a cart line; promotions work on units so that quantities can be
+// split between promotions
+struct Unit {
+    line: usize,
+    price: f64,
+    discount: f64,
+    // Taken by a non-stackable promotion; nothing else may discount it
+    claimed: bool,
+    // Discounted by a stackable promotion; non-stackable promotions skip it
+    touched: bool,
+}
+
+impl Unit {
+    fn remaining(&self) -> f64 {
+        self.price - self.discount
+    }
+}
+
+impl PromotionRules {
+    fn matches(&self, line: &CartLine) -> bool {
+        if self.product_ids.is_empty() && self.categories.is_empty() {
+            return true;
+        }
+        self.product_ids.contains(&line.product_id)
+            || line.category.as_ref().map(|c| self.categories.contains(c)).unwrap_or(false)
+    }
+
+    fn in_time_window(&self, now: NaiveDateTime) -> bool {
+        if !self.days_of_week.is_empty() && !self.days_of_week.contains(&now.weekday().number_from_monday()) {
+            return false;
+        }
+        let start = self.start_time.as_deref().and_then(|t| NaiveTime::parse_from_str(t, "%H:%M").ok());
+        let end = self.end_time.as_deref().and_then(|t| NaiveTime::parse_from_str(t, "%H:%M").ok());
+        let time = now.time();
+        match (start, end) {
+            (Some(start), Some(end)) if start <= end => time >= start && time < end,
+            // Window crossing midnight, e.g. 22:00-02:00
+            (Some(start), Some(end)) => time >= start || time < end,
+            (Some(start), None) => time >= start,
+            (None, Some(end)) => time < end,
+            (None, None) => true,
+        }
+    }
+}
+
+// Check that a promotion's rules contain what its type needs
+pub fn validate_rules(promo_type: &str, rules: &serde_json::Value) -> Result<PromotionRules, String> {
+    let parsed: PromotionRules = serde_json::from_value(rules.clone()).map_err(|e| format!("Invalid promotion rules: {}", e))?;
+
+    let valid = match promo_type {
+        "buy_x_get_y" => parsed.buy_quantity > 0 && parsed.get_quantity > 0,
+        "mix_and_match" => parsed.quantity > 0 && parsed.price.map(|p| p >= 0.0).unwrap_or(false),
+        "bundle" => !parsed.components.is_empty() && parsed.components.iter().all(|c| c.quantity > 0) && parsed.price.map(|p| p >= 0.0).unwrap_or(false),
+        "percent_off" => parsed.percent.map(|p| p > 0.0 && p <= 100.0).unwrap_or(false),
+        "fixed_price" => parsed.price.map(|p| p >= 0.0).unwrap_or(false),
+        other => return Err(format!("Unknown promotion type: {}", other)),
+    };
+
+    if valid {
+        Ok(parsed)
+    } else {
+        Err(format!("Promotion rules are incomplete for type {}", promo_type))
+    }
+}
+
+// Indexes of units a promotion may use, highest price first (ties by cart order)
+fn eligible_units(units: &[Unit], lines: &[CartLine], rules: &PromotionRules, stackable: bool) -> Vec<usize> {
+    let mut eligible: Vec<usize> = (0..units.len())
+        .filter(|&i| {
+            let unit = &units[i];
+            !unit.claimed && (stackable || !unit.touched) && unit.remaining() > 0.0 && rules.matches(&lines[unit.line])
+        })
+        .collect();
+    eligible.sort_by(|&a, &b| {
+        units[b].price.partial_cmp(&units[a].price).unwrap_or(std::cmp::Ordering::Equal).then(a.cmp(&b))
+    });
+    eligible
+}
+
+// Split a group discount over its units in proportion to price, in whole cents
+fn spread_discount(units: &[Unit], group: &[usize], discount: f64) -> Vec<(usize, f64)> {
+    let total: f64 = group.iter().map(|&i| units[i].remaining()).sum();
+    let mut shares = Vec::with_capacity(group.len());
+    let mut given = 0.0;
+    for (n, &i) in group.iter().enumerate() {
+        let share = if n + 1 == group.len() {
+            round_money(discount - given)
+        } else {
+            round_money(discount * units[i].remaining() / total)
+        };
+        given += share;
+        shares.push((i, share));
+    }
+    shares
+}
+
+// Discounts (unit index, amount) that one promotion gives on the current units
+fn evaluate_promotion(promo_type: &str, rules: &PromotionRules, units: &[Unit], lines: &[CartLine], eligible: &[usize]) -> Vec<(usize, f64)> {
+    let mut discounts = Vec::new();
+
+    match promo_type {
+        "buy_x_get_y" => {
+            // In each group of buy + get units, the cheapest `get` units are discounted
+            let group_size = (rules.buy_quantity + rules.get_quantity) as usize;
+            let percent = rules.get_discount_percent.unwrap_or(100.0).clamp(0.0, 100.0);
+            for group in eligible.chunks_exact(group_size) {
+                for &i in &group[rules.buy_quantity as usize..] {
+                    discounts.push((i, round_money(units[i].remaining() * percent / 100.0)));
+                }
+                // Units bought to qualify are consumed by the promotion as well
+                for &i in &group[..rules.buy_quantity as usize] {
+                    discounts.push((i, 0.0));
+                }
+            }
+        }
+        "mix_and_match" => {
+            let price = rules.price.unwrap_or(0.0);
+            for group in eligible.chunks_exact(rules.quantity as usize) {
+                let regular: f64 = group.iter().map(|&i| units[i].remaining()).sum();
+                if regular > price {
+                    discounts.extend(spread_discount(units, group, round_money(regular - price)));
+                }
+            }
+        }
+        "bundle" => {
+            let price = rules.price.unwrap_or(0.0);
+            let mut available: Vec<usize> = eligible.to_vec();
+            loop {
+                // Take the required quantity of each component, or stop when one is short
+                let mut group = Vec::new();
+                for component in &rules.components {
+                    let picked: Vec<usize> = available
+                        .iter()
+                        .copied()
+                        .filter(|&i| !group.contains(&i) && lines[units[i].line].product_id == component.product_id)
+                        .take(component.quantity as usize)
+                        .collect();
+                    if picked.len() < component.quantity as usize {
+                        group.clear();
+                        break;
+                    }
+                    group.extend(picked);
+                }
+                if group.is_empty() {
+                    break;
+                }
+                available.retain(|i| !group.contains(i));
+                let regular: f64 = group.iter().map(|&i| units[i].remaining()).sum();
+                if regular > price {
+                    discounts.extend(spread_discount(units, &group, round_money(regular - price)));
+                }
+            }
+        }
+        "percent_off" => {
+            let percent = rules.percent.unwrap_or(0.0).clamp(0.0, 100.0);
+            for &i in eligible {
+                discounts.push((i, round_money(units[i].remaining() * percent / 100.0)));
+            }
+        }
+        "fixed_price" => {
+            let price = rules.price.unwrap_or(0.0);
+            for &i in eligible {
+                if units[i].remaining() > price {
+                    discounts.push((i, round_money(units[i].remaining() - price)));
+                }
+            }
+        }
+        _ => {}
+    }
+
+    discounts
+}
+
+// Evaluate promotions over a cart. Promotions run by priority (highest first,
+// ties broken by id) so the result is deterministic. Non-stackable promotions
+// only use units no other promotion has discounted and then claim them;
+// stackable promotions may discount any unit not claimed by a non-stackable one.
+pub fn evaluate(lines: &[CartLine], promotions: &[Promotion], now: NaiveDateTime, is_member: bool) -> Vec<AppliedPromotion> {
+    let mut units: Vec<Unit> = Vec::new();
+    for (line_index, line) in lines.iter().enumerate() {
+        for _ in 0..line.quantity.max(0) {
+            units.push(Unit { line: line_index, price: line.unit_price, discount: 0.0, claimed: false, touched: false });
+        }
+    }
+
+    let mut ordered: Vec<&Promotion> = promotions.iter().filter(|p| p.is_active).collect();
+    ordered.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.id.cmp(&b.id)));
+
+    // (promotion position, line) -> amount, kept ordered for stable output
+    let mut totals: BTreeMap<(usize, usize), f64> = BTreeMap::new();
+
+    for (position, promotion) in ordered.iter().enumerate() {
+        let rules = match validate_rules(&promotion.promo_type, &promotion.rules) {
+            Ok(rules) => rules,
+            Err(_) => continue,
+        };
+        if (rules.member_only && !is_member) || !rules.in_time_window(now) {
+            continue;
+        }
+
+        let eligible = eligible_units(&units, lines, &rules, promotion.stackable);
+        for (i, amount) in evaluate_promotion(&promotion.promo_type, &rules, &units, lines, &eligible) {
+            let amount = amount.min(units[i].remaining()).max(0.0);
+            let unit = &mut units[i];
+            unit.discount += amount;
+            if promotion.stackable {
+                unit.touched = true;
+            } else {
+                unit.claimed = true;
+            }
+            if amount > 0.0 {
+                *totals.entry((position, unit.line)).or_insert(0.0) += amount;
+            }
+        }
+    }
+
+    totals
+        .into_iter()
+        .map(|((position, line_index), amount)| AppliedPromotion {
+            promotion_id: ordered[position].id.clone(),
+            name: ordered[position].name.clone(),
+            promo_type: ordered[position].promo_type.clone(),
+            line_index,
+            amount: round_money(amount),
+        })
+        .collect()
+}
+
+// Active promotions whose date range includes now
+pub fn load_active_promotions(conn: &Connection) -> Result<Vec<Promotion>, String> {
+    let now = Utc::now().to_rfc3339();
+    let mut stmt = conn.prepare(
+        "SELECT id, name, promo_type, rules, priority, stackable, is_active, starts_at, ends_at
+         FROM promotions
+         WHERE is_active = 1 AND (starts_at IS NULL OR starts_at <= ?1) AND (ends_at IS NULL OR ends_at > ?1)"
+    ).map_err(|e| e.to_string())?;
+
+    let promotion_iter = stmt.query_map(params![now], map_promotion).map_err(|e| e.to_string())?;
+
+    let mut promotions = Vec::new();
+    for promotion in promotion_iter {
+        promotions.push(promotion.map_err(|e| e.to_string())?);
+    }
+
+    Ok(promotions)
+}
+
+fn map_promotion(row: &rusqlite::Row) -> rusqlite::Result<Promotion> {
+    Ok(Promotion {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        promo_type: row.get(2)?,
+        rules: serde_json::from_str(row.get::<_, String>(3)?.as_str()).unwrap_or_default(),
+        priority: row.get(4)?,
+        stackable: row.get(5)?,
+        is_active: row.get(6)?,
+        starts_at: row.get(7)?,
+        ends_at: row.get(8)?,
+    })
+}
+
+// Build evaluator lines for transaction items, looking up product categories
+pub fn cart_lines(conn: &Connection, items: &[TransactionItem]) -> Result<Vec<CartLine>, String> {
+    let mut stmt = conn.prepare("SELECT category, sold_by_weight FROM products WHERE id = ?1").map_err(|e| e.to_string())?;
+    let mut lines = Vec::with_capacity(items.len());
+
+    for item in items {
+        let (category, sold_by_weight) = match stmt.query_row(params![item.product_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?))) {
+            Ok((category, sold_by_weight)) => (Some(category), sold_by_weight),
+            Err(rusqlite::Error::QueryReturnedNoRows) => (None, false),
+            Err(e) => return Err(e.to_string()),
+        };
+        // Promotions count whole units, so weighed lines take no part in them
+        lines.push(CartLine {
+            product_id: item.product_id.clone(),
+            category,
+            quantity: if sold_by_weight { 0 } else { item.quantity as i32 },
+            unit_price: item.price,
+        });
+    }
+
+    Ok(lines)
+}
+
+// Promotion discount per cart line for a sale happening now
+pub fn apply_to_items(conn: &Connection, items: &[TransactionItem], is_member: bool) -> Result<Vec<AppliedPromotion>, String> {
+    let lines = cart_lines(conn, items)?;
+    let promotions = load_active_promotions(conn)?;
+
+    Ok(evaluate(&lines, &promotions, Local::now().naive_local(), is_member))
+}
+
+// Price a cart the way create_transaction will charge it; called by the
+// frontend before payment so the cashier collects the same total
+#[tauri::command]
+pub fn price_cart(items: Vec<TransactionItem>, discount: Option<DiscountInput>, customer_id: Option<String>, payment_method: Option<String>, db: State<Database>) -> Result<PricedCart, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let is_member = crate::customers::is_member(&conn, customer_id.as_deref())?;
+    let mut items = items;
+    let sale = crate::price_sale(&conn, &mut items, discount.as_ref(), is_member)?;
+
+    let lines: Vec<PricedLine> = items
+        .iter()
+        .zip(&sale.line_discounts)
+        .map(|(item, (gross, promotion_discount, manual_discount))| PricedLine {
+            product_id: item.product_id.clone(),
+            quantity: item.quantity,
+            unit_price: item.price,
+            gross: *gross,
+            promotion_discount: *promotion_discount,
+            manual_discount: *manual_discount,
+            net: round_money(gross - promotion_discount - manual_discount),
+        })
+        .collect();
+
+    let gross_total = round_money(lines.iter().map(|l| l.gross).sum());
+    let promotion_total = round_money(lines.iter().map(|l| l.promotion_discount).sum());
+    let amount_due = match &payment_method {
+        Some(method) => crate::payments::amount_due(
+            sale.total,
+            method,
+            &crate::payments::load_payment_methods(&conn)?,
+            &crate::payments::cash_rounding(&conn)?,
+        ),
+        None => sale.total,
+    };
+
+    Ok(PricedCart {
+        lines,
+        promotions: sale.applied_promotions,
+        gross_total,
+        promotion_total,
+        discount_total: sale.discount_total,
+        tax_mode: sale.tax_mode.as_str().to_string(),
+        tax_total: sale.tax_total,
+        total: sale.total,
+        amount_due,
+    })
+}
+
+#[tauri::command]
+pub fn get_promotions(db: State<Database>) -> Result<Vec<Promotion>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(
+        "SELECT id, name, promo_type, rules, priority, stackable, is_active, starts_at, ends_at
+         FROM promotions
+         ORDER BY priority DESC, id"
+    ).map_err(|e| e.to_string())?;
+
+    let promotion_iter = stmt.query_map([], map_promotion).map_err(|e| e.to_string())?;
+
+    let mut promotions = Vec::new();
+    for promotion in promotion_iter {
+        promotions.push(promotion.map_err(|e| e.to_string())?);
+    }
+
+    Ok(promotions)
+}
+
+#[tauri::command]
+pub fn create_promotion(promotion: PromotionInput, db: State<Database>) -> Result<String, String> {
+    validate_rules(&promotion.promo_type, &promotion.rules)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let id = Uuid::new_v4().to_string();
+
+    conn.execute(
+        "INSERT INTO promotions (id, name, promo_type, rules, priority, stackable, is_active, starts_at, ends_at, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            id,
+            promotion.name,
+            promotion.promo_type,
+            promotion.rules.to_string(),
+            promotion.priority.unwrap_or(0),
+            promotion.stackable.unwrap_or(false),
+            promotion.is_active.unwrap_or(true),
+            promotion.starts_at,
+            promotion.ends_at,
+            Utc::now().to_rfc3339()
+        ]
+    ).map_err(|e| e.to_string())?;
+
+    Ok(id)
+}
+
+#[tauri::command]
+pub fn update_promotion(id: String, promotion: PromotionInput, db: State<Database>) -> Result<(), String> {
+    validate_rules(&promotion.promo_type, &promotion.rules)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "UPDATE promotions SET name = ?1, promo_type = ?2, rules = ?3, priority = ?4, stackable = ?5, is_active = ?6, starts_at = ?7, ends_at = ?8
+         WHERE id = ?9",
+        params![
+            promotion.name,
+            promotion.promo_type,
+            promotion.rules.to_string(),
+            promotion.priority.unwrap_or(0),
+            promotion.stackable.unwrap_or(false),
+            promotion.is_active.unwrap_or(true),
+            promotion.starts_at,
+            promotion.ends_at,
+            id
+        ]
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+// Promotions are deactivated rather than deleted so past sales keep their reference
+#[tauri::command]
+pub fn delete_promotion(id: String, db: State<Database>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    conn.execute("UPDATE promotions SET is_active = 0 WHERE id = ?1", params![id]).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
@@ -0,0 +1,533 @@
+// Suppliers, purchase orders and goods receiving.
+//
+// A purchase order lists products with ordered quantities and expected unit
+// costs. Goods can be received in several deliveries; every delivery is kept in
+// `purchase_receipts`, posts a 'receipt' movement to the inventory ledger and
+// moves the product's cost price to the weighted average of the stock on hand
+// and the delivery. Closing an order gives up on anything not yet delivered.
+
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::Database;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Supplier {
+    pub id: String,
+    pub name: String,
+    pub contact_name: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub address: Option<String>,
+    pub notes: Option<String>,
+    pub is_active: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplierInput {
+    pub name: String,
+    pub contact_name: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub address: Option<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurchaseOrderLine {
+    pub id: String,
+    pub product_id: String,
+    pub product_name: String,
+    pub quantity_ordered: i32,
+    pub quantity_received: i32,
+    pub unit_cost: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurchaseOrder {
+    pub id: String,
+    pub po_number: String,
+    pub supplier_id: String,
+    pub supplier_name: String,
+    // open, partially_received, received or closed
+    pub status: String,
+    pub expected_date: Option<String>,
+    pub notes: Option<String>,
+    pub created_by: String,
+    pub created_at: String,
+    pub closed_at: Option<String>,
+    pub lines: Vec<PurchaseOrderLine>,
+    pub total_cost: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurchaseOrderLineInput {
+    pub product_id: String,
+    pub quantity: i32,
+    pub unit_cost: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurchaseOrderInput {
+    pub supplier_id: String,
+    pub expected_date: Option<String>,
+    pub notes: Option<String>,
+    pub user_id: String,
+    pub lines: Vec<PurchaseOrderLineInput>,
+}
+
+// A delivered quantity for one order line; `unit_cost` overrides the ordered
+// cost when the invoice differs
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReceiveLineInput {
+    pub line_id: String,
+    pub quantity: i32,
+    pub unit_cost: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplierOutstanding {
+    pub supplier_id: String,
+    pub supplier_name: String,
+    pub order_count: i64,
+    pub outstanding_quantity: i32,
+    pub outstanding_value: f64,
+    pub orders: Vec<PurchaseOrder>,
+}
+
+const SUPPLIER_COLUMNS: &str = "id, name, contact_name, phone, email, address, notes, is_active, created_at, updated_at";
+
+const PURCHASE_ORDER_COLUMNS: &str = "po.id, po.po_number, po.supplier_id, s.name, po.status, po.expected_date, po.notes, po.created_by, po.created_at, po.closed_at";
+
+fn clean(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+fn map_supplier(row: &rusqlite::Row) -> rusqlite::Result<Supplier> {
+    Ok(Supplier {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        contact_name: row.get(2)?,
+        phone: row.get(3)?,
+        email: row.get(4)?,
+        address: row.get(5)?,
+        notes: row.get(6)?,
+        is_active: row.get(7)?,
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+    })
+}
+
+fn map_purchase_order(row: &rusqlite::Row) -> rusqlite::Result<PurchaseOrder> {
+    Ok(PurchaseOrder {
+        id: row.get(0)?,
+        po_number: row.get(1)?,
+        supplier_id: row.get(2)?,
+        supplier_name: row.get(3)?,
+        status: row.get(4)?,
+        expected_date: row.get(5)?,
+        notes: row.get(6)?,
+        created_by: row.get(7)?,
+        created_at: row.get(8)?,
+        closed_at: row.get(9)?,
+        lines: Vec::new(),
+        total_cost: 0.0,
+    })
+}
+
+fn find_supplier(conn: &Connection, supplier_id: &str) -> Result<Supplier, String> {
+    match conn.query_row(
+        &format!("SELECT {} FROM suppliers WHERE id = ?1", SUPPLIER_COLUMNS),
+        params![supplier_id],
+        map_supplier
+    ) {
+        Ok(supplier) => Ok(supplier),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Err("Supplier not found".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn load_lines(conn: &Connection, purchase_order_id: &str) -> Result<Vec<PurchaseOrderLine>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT l.id, l.product_id, p.name, l.quantity_ordered, l.quantity_received, l.unit_cost
+         FROM purchase_order_lines l
+         JOIN products p ON p.id = l.product_id
+         WHERE l.purchase_order_id = ?1
+         ORDER BY l.rowid"
+    ).map_err(|e| e.to_string())?;
+
+    let line_iter = stmt.query_map(params![purchase_order_id], |row| {
+        Ok(PurchaseOrderLine {
+            id: row.get(0)?,
+            product_id: row.get(1)?,
+            product_name: row.get(2)?,
+            quantity_ordered: row.get(3)?,
+            quantity_received: row.get(4)?,
+            unit_cost: row.get(5)?,
+        })
+    }).map_err(|e| e.to_string())?;
+
+    let mut lines = Vec::new();
+    for line in line_iter {
+        lines.push(line.map_err(|e| e.to_string())?);
+    }
+
+    Ok(lines)
+}
+
+fn with_lines(conn: &Connection, mut order: PurchaseOrder) -> Result<PurchaseOrder, String> {
+    order.lines = load_lines(conn, &order.id)?;
+    order.total_cost = crate::pricing::round_money(order.lines.iter().map(|l| l.quantity_ordered as f64 * l.unit_cost).sum());
+    Ok(order)
+}
+
+fn query_purchase_orders(conn: &Connection, supplier_id: Option<&str>, statuses: &[&str]) -> Result<Vec<PurchaseOrder>, String> {
+    let status_list = statuses.iter().map(|s| format!("'{}'", s)).collect::<Vec<_>>().join(", ");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM purchase_orders po
+         JOIN suppliers s ON s.id = po.supplier_id
+         WHERE (?1 IS NULL OR po.supplier_id = ?1) AND po.status IN ({})
+         ORDER BY po.created_at DESC",
+        PURCHASE_ORDER_COLUMNS, status_list
+    )).map_err(|e| e.to_string())?;
+
+    let order_iter = stmt.query_map(params![supplier_id], map_purchase_order).map_err(|e| e.to_string())?;
+
+    let mut orders = Vec::new();
+    for order in order_iter {
+        orders.push(with_lines(conn, order.map_err(|e| e.to_string())?)?);
+    }
+
+    Ok(orders)
+}
+
+pub fn find_purchase_order(conn: &Connection, purchase_order_id: &str) -> Result<PurchaseOrder, String> {
+    let order = match conn.query_row(
+        &format!(
+            "SELECT {} FROM purchase_orders po JOIN suppliers s ON s.id = po.supplier_id WHERE po.id = ?1",
+            PURCHASE_ORDER_COLUMNS
+        ),
+        params![purchase_order_id],
+        map_purchase_order
+    ) {
+        Ok(order) => order,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Err("Purchase order not found".to_string()),
+        Err(e) => return Err(e.to_string()),
+    };
+
+    with_lines(conn, order)
+}
+
+// Average unit cost after receiving `quantity` at `unit_cost` on top of the
+// current stock. Stock at or below zero carries no cost, so the delivery sets it.
+fn weighted_average_cost(stock: i32, cost: f64, quantity: i32, unit_cost: f64) -> f64 {
+    let on_hand = stock.max(0) as f64;
+    let total = on_hand + quantity as f64;
+    if total <= 0.0 {
+        return unit_cost;
+    }
+    ((on_hand * cost + quantity as f64 * unit_cost) / total * 10000.0).round() / 10000.0
+}
+
+fn validate_supplier(supplier: &SupplierInput) -> Result<(), String> {
+    if supplier.name.trim().is_empty() {
+        return Err("Supplier name is required".to_string());
+    }
+    if let Some(email) = clean(supplier.email.clone()) {
+        if !email.contains('@') {
+            return Err(format!("Invalid email address: {}", email));
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_suppliers(include_inactive: Option<bool>, db: State<Database>) -> Result<Vec<Supplier>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM suppliers WHERE is_active = 1 OR ?1 ORDER BY name",
+        SUPPLIER_COLUMNS
+    )).map_err(|e| e.to_string())?;
+
+    let supplier_iter = stmt.query_map(params![include_inactive.unwrap_or(false)], map_supplier).map_err(|e| e.to_string())?;
+
+    let mut suppliers = Vec::new();
+    for supplier in supplier_iter {
+        suppliers.push(supplier.map_err(|e| e.to_string())?);
+    }
+
+    Ok(suppliers)
+}
+
+#[tauri::command]
+pub fn create_supplier(supplier: SupplierInput, db: State<Database>) -> Result<Supplier, String> {
+    validate_supplier(&supplier)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let supplier_id = Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO suppliers (id, name, contact_name, phone, email, address, notes, is_active, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 1, ?8, ?8)",
+        params![supplier_id, supplier.name.trim(), clean(supplier.contact_name), clean(supplier.phone), clean(supplier.email), clean(supplier.address), clean(supplier.notes), Utc::now().to_rfc3339()]
+    ).map_err(|e| e.to_string())?;
+
+    find_supplier(&conn, &supplier_id)
+}
+
+#[tauri::command]
+pub fn update_supplier(supplier_id: String, supplier: SupplierInput, db: State<Database>) -> Result<Supplier, String> {
+    validate_supplier(&supplier)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let updated = conn.execute(
+        "UPDATE suppliers SET name = ?1, contact_name = ?2, phone = ?3, email = ?4, address = ?5, notes = ?6, updated_at = ?7
+         WHERE id = ?8",
+        params![supplier.name.trim(), clean(supplier.contact_name), clean(supplier.phone), clean(supplier.email), clean(supplier.address), clean(supplier.notes), Utc::now().to_rfc3339(), supplier_id]
+    ).map_err(|e| e.to_string())?;
+
+    if updated == 0 {
+        return Err("Supplier not found".to_string());
+    }
+
+    find_supplier(&conn, &supplier_id)
+}
+
+// Suppliers are deactivated rather than deleted so their orders keep the link
+#[tauri::command]
+pub fn delete_supplier(supplier_id: String, db: State<Database>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "UPDATE suppliers SET is_active = 0, updated_at = ?1 WHERE id = ?2",
+        params![Utc::now().to_rfc3339(), supplier_id]
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn create_purchase_order(order: PurchaseOrderInput, db: State<Database>) -> Result<PurchaseOrder, String> {
+    if order.lines.is_empty() {
+        return Err("A purchase order needs at least one line".to_string());
+    }
+    if order.lines.iter().any(|l| l.quantity <= 0 || l.unit_cost < 0.0) {
+        return Err("Order quantities must be positive and costs not negative".to_string());
+    }
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    if !find_supplier(&tx, &order.supplier_id)?.is_active {
+        return Err("Supplier is inactive".to_string());
+    }
+
+    let count: i64 = tx.query_row("SELECT COUNT(*) FROM purchase_orders", [], |row| row.get(0)).map_err(|e| e.to_string())?;
+    let purchase_order_id = Uuid::new_v4().to_string();
+
+    tx.execute(
+        "INSERT INTO purchase_orders (id, po_number, supplier_id, status, expected_date, notes, created_by, created_at)
+         VALUES (?1, ?2, ?3, 'open', ?4, ?5, ?6, ?7)",
+        params![purchase_order_id, format!("PO-{:06}", count + 1), order.supplier_id, clean(order.expected_date), clean(order.notes), order.user_id, Utc::now().to_rfc3339()]
+    ).map_err(|e| e.to_string())?;
+
+    for line in &order.lines {
+        tx.execute(
+            "INSERT INTO purchase_order_lines (id, purchase_order_id, product_id, quantity_ordered, quantity_received, unit_cost)
+             VALUES (?1, ?2, ?3, ?4, 0, ?5)",
+            params![Uuid::new_v4().to_string(), purchase_order_id, line.product_id, line.quantity, line.unit_cost]
+        ).map_err(|e| e.to_string())?;
+    }
+
+    let purchase_order = find_purchase_order(&tx, &purchase_order_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(purchase_order)
+}
+
+#[tauri::command]
+pub fn get_purchase_order(purchase_order_id: String, db: State<Database>) -> Result<PurchaseOrder, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    find_purchase_order(&conn, &purchase_order_id)
+}
+
+#[tauri::command]
+pub fn list_purchase_orders(supplier_id: Option<String>, status: Option<String>, db: State<Database>) -> Result<Vec<PurchaseOrder>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let statuses: Vec<&str> = match status.as_deref() {
+        Some(status @ ("open" | "partially_received" | "received" | "closed")) => vec![status],
+        Some(status) => return Err(format!("Unknown purchase order status: {}", status)),
+        None => vec!["open", "partially_received", "received", "closed"],
+    };
+
+    query_purchase_orders(&conn, supplier_id.as_deref(), &statuses)
+}
+
+// Book a delivery against an order. With no lines given, everything still
+// outstanding is received at the ordered cost.
+#[tauri::command]
+pub fn receive_purchase_order(purchase_order_id: String, lines: Option<Vec<ReceiveLineInput>>, user_id: String, db: State<Database>) -> Result<PurchaseOrder, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let order = find_purchase_order(&tx, &purchase_order_id)?;
+
+    if order.status != "open" && order.status != "partially_received" {
+        return Err(format!("Purchase order is {}", order.status.replace('_', " ")));
+    }
+
+    let receipts = match lines {
+        Some(lines) => lines,
+        None => order.lines.iter()
+            .filter(|l| l.quantity_received < l.quantity_ordered)
+            .map(|l| ReceiveLineInput { line_id: l.id.clone(), quantity: l.quantity_ordered - l.quantity_received, unit_cost: None })
+            .collect(),
+    };
+    if receipts.iter().all(|r| r.quantity == 0) {
+        return Err("Nothing to receive".to_string());
+    }
+    // Lines are checked against the order as loaded, so each may appear once
+    for (index, receipt) in receipts.iter().enumerate() {
+        if receipts[..index].iter().any(|r| r.line_id == receipt.line_id) {
+            return Err("Each order line can only be received once per delivery".to_string());
+        }
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let receipt_label = format!("Receipt {}", order.po_number);
+
+    for receipt in receipts.iter().filter(|r| r.quantity != 0) {
+        let line = order.lines.iter()
+            .find(|l| l.id == receipt.line_id)
+            .ok_or_else(|| "Order line not found".to_string())?;
+        if receipt.quantity < 0 || line.quantity_received + receipt.quantity > line.quantity_ordered {
+            return Err(format!("Cannot receive {} of {}: {} outstanding", receipt.quantity, line.product_name, line.quantity_ordered - line.quantity_received));
+        }
+        let unit_cost = receipt.unit_cost.unwrap_or(line.unit_cost);
+        if unit_cost < 0.0 {
+            return Err("Unit cost cannot be negative".to_string());
+        }
+
+        tx.execute(
+            "UPDATE purchase_order_lines SET quantity_received = quantity_received + ?1 WHERE id = ?2",
+            params![receipt.quantity, line.id]
+        ).map_err(|e| e.to_string())?;
+
+        tx.execute(
+            "INSERT INTO purchase_receipts (id, purchase_order_id, purchase_order_line_id, product_id, quantity, unit_cost, user_id, received_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![Uuid::new_v4().to_string(), purchase_order_id, line.id, line.product_id, receipt.quantity, unit_cost, user_id, now]
+        ).map_err(|e| e.to_string())?;
+
+        let (stock_before, cost_before): (i32, f64) = tx.query_row(
+            "SELECT stock, cost_price FROM products WHERE id = ?1",
+            params![line.product_id],
+            |row| Ok((row.get(0)?, row.get(1)?))
+        ).map_err(|e| e.to_string())?;
+
+        crate::inventory::record_movement(&tx, &line.product_id, "receipt", receipt.quantity, Some("purchase_order"), Some(&purchase_order_id), Some(&user_id), Some(&receipt_label))?;
+
+        tx.execute(
+            "UPDATE products SET cost_price = ?1 WHERE id = ?2",
+            params![weighted_average_cost(stock_before, cost_before, receipt.quantity, unit_cost), line.product_id]
+        ).map_err(|e| e.to_string())?;
+    }
+
+    let outstanding: i64 = tx.query_row(
+        "SELECT COUNT(*) FROM purchase_order_lines WHERE purchase_order_id = ?1 AND quantity_received < quantity_ordered",
+        params![purchase_order_id],
+        |row| row.get(0)
+    ).map_err(|e| e.to_string())?;
+
+    tx.execute(
+        "UPDATE purchase_orders SET status = ?1 WHERE id = ?2",
+        params![if outstanding == 0 { "received" } else { "partially_received" }, purchase_order_id]
+    ).map_err(|e| e.to_string())?;
+
+    let purchase_order = find_purchase_order(&tx, &purchase_order_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(purchase_order)
+}
+
+// Close an order; quantities not delivered by now are no longer expected
+#[tauri::command]
+pub fn close_purchase_order(purchase_order_id: String, user_id: String, db: State<Database>) -> Result<PurchaseOrder, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let order = find_purchase_order(&conn, &purchase_order_id)?;
+
+    if order.status == "closed" {
+        return Err("Purchase order is already closed".to_string());
+    }
+
+    conn.execute(
+        "UPDATE purchase_orders SET status = 'closed', closed_at = ?1, closed_by = ?2 WHERE id = ?3",
+        params![Utc::now().to_rfc3339(), user_id, purchase_order_id]
+    ).map_err(|e| e.to_string())?;
+
+    find_purchase_order(&conn, &purchase_order_id)
+}
+
+// Orders still waiting for goods, grouped by supplier, with the quantity and
+// cost not yet delivered
+#[tauri::command]
+pub fn get_outstanding_purchase_orders(supplier_id: Option<String>, db: State<Database>) -> Result<Vec<SupplierOutstanding>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let orders = query_purchase_orders(&conn, supplier_id.as_deref(), &["open", "partially_received"])?;
+
+    let mut suppliers: Vec<SupplierOutstanding> = Vec::new();
+    for order in orders {
+        let quantity: i32 = order.lines.iter().map(|l| l.quantity_ordered - l.quantity_received).sum();
+        let value: f64 = order.lines.iter().map(|l| (l.quantity_ordered - l.quantity_received) as f64 * l.unit_cost).sum();
+
+        let index = match suppliers.iter().position(|s| s.supplier_id == order.supplier_id) {
+            Some(index) => index,
+            None => {
+                suppliers.push(SupplierOutstanding {
+                    supplier_id: order.supplier_id.clone(),
+                    supplier_name: order.supplier_name.clone(),
+                    order_count: 0,
+                    outstanding_quantity: 0,
+                    outstanding_value: 0.0,
+                    orders: Vec::new(),
+                });
+                suppliers.len() - 1
+            }
+        };
+        let supplier = &mut suppliers[index];
+        supplier.order_count += 1;
+        supplier.outstanding_quantity += quantity;
+        supplier.outstanding_value = crate::pricing::round_money(supplier.outstanding_value + value);
+        supplier.orders.push(order);
+    }
+
+    suppliers.sort_by(|a, b| a.supplier_name.cmp(&b.supplier_name));
+
+    Ok(suppliers)
+}
+
+// Set a product's cost by hand, e.g. for stock that was never received through
+// a purchase order. Later receipts average from this figure.
+#[tauri::command]
+pub fn set_product_cost(product_id: String, cost_price: f64, db: State<Database>) -> Result<(), String> {
+    if !cost_price.is_finite() || cost_price < 0.0 {
+        return Err("Cost price cannot be negative".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let updated = conn.execute(
+        "UPDATE products SET cost_price = ?1 WHERE id = ?2",
+        params![cost_price, product_id]
+    ).map_err(|e| e.to_string())?;
+
+    if updated == 0 {
+        return Err(format!("Product {} not found", product_id));
+    }
+
+    Ok(())
+}
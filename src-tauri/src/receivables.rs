@@ -0,0 +1,429 @@
+// Customer accounts receivable (kasbon).
+//
+// Customers with a credit limit may pay with the "on_account" tender. Each
+// account is a ledger in `ar_ledger`: charges from sales are positive,
+// payments received at the counter are negative and adjustments (such as
+// reversals of voided sales) may be either. The balance owed is the sum.
+// For aging, a reversal settles the charge of its own sale and payments settle
+// the oldest remaining charges first.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::payments::Payment;
+use crate::pricing::round_money;
+use crate::{Database, LoginData};
+
+pub const ON_ACCOUNT_METHOD: &str = "on_account";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArEntry {
+    pub id: String,
+    pub customer_id: String,
+    pub transaction_id: Option<String>,
+    pub entry_type: String,
+    pub amount: f64,
+    pub payment_method: Option<String>,
+    pub reference: Option<String>,
+    pub note: Option<String>,
+    pub shift_id: Option<String>,
+    pub user_id: Option<String>,
+    pub created_at: String,
+}
+
+// A payment towards an account taken at the counter
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArPaymentInput {
+    pub customer_id: String,
+    pub amount: f64,
+    pub payment_method: String,
+    pub reference: Option<String>,
+    pub shift_id: Option<String>,
+    pub user_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArAccount {
+    pub customer_id: String,
+    pub customer_name: String,
+    pub credit_limit: f64,
+    pub balance: f64,
+    pub available_credit: f64,
+}
+
+// Outstanding balance split by age of the unpaid charges
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArAging {
+    pub customer_id: String,
+    pub customer_name: String,
+    pub balance: f64,
+    pub current: f64,
+    pub days_31_60: f64,
+    pub days_61_90: f64,
+    pub over_90: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatementLine {
+    pub entry: ArEntry,
+    pub balance: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArStatement {
+    pub account: ArAccount,
+    pub start_date: String,
+    pub end_date: String,
+    pub opening_balance: f64,
+    pub lines: Vec<StatementLine>,
+    pub closing_balance: f64,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn insert_entry(
+    conn: &Connection,
+    customer_id: &str,
+    transaction_id: Option<&str>,
+    entry_type: &str,
+    amount: f64,
+    payment_method: Option<&str>,
+    reference: Option<&str>,
+    note: Option<&str>,
+    shift_id: Option<&str>,
+    user_id: Option<&str>,
+    timestamp: &str,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO ar_ledger (id, customer_id, transaction_id, entry_type, amount, payment_method, reference, note, shift_id, user_id, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![Uuid::new_v4().to_string(), customer_id, transaction_id, entry_type, round_money(amount), payment_method, reference, note, shift_id, user_id, timestamp]
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+pub fn account_balance(conn: &Connection, customer_id: &str) -> Result<f64, String> {
+    conn.query_row(
+        "SELECT COALESCE(SUM(amount), 0.0) FROM ar_ledger WHERE customer_id = ?1",
+        params![customer_id],
+        |row| row.get::<_, f64>(0)
+    ).map(round_money).map_err(|e| e.to_string())
+}
+
+fn load_account(conn: &Connection, customer_id: &str) -> Result<ArAccount, String> {
+    let (customer_name, credit_limit): (String, f64) = match conn.query_row(
+        "SELECT name, credit_limit FROM customers WHERE id = ?1",
+        params![customer_id],
+        |row| Ok((row.get(0)?, row.get(1)?))
+    ) {
+        Ok(row) => row,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Err("Customer not found".to_string()),
+        Err(e) => return Err(e.to_string()),
+    };
+    let balance = account_balance(conn, customer_id)?;
+
+    Ok(ArAccount {
+        customer_id: customer_id.to_string(),
+        customer_name,
+        credit_limit,
+        balance,
+        available_credit: round_money((credit_limit - balance).max(0.0)),
+    })
+}
+
+// Charge on-account tenders of a sale to the customer's account
+pub fn charge_tenders(
+    conn: &Connection,
+    transaction_id: &str,
+    customer_id: Option<&str>,
+    payments: &[Payment],
+    user_id: Option<&str>,
+    timestamp: &str,
+) -> Result<(), String> {
+    let amount: f64 = round_money(payments.iter().filter(|p| p.method == ON_ACCOUNT_METHOD).map(|p| p.amount).sum());
+    if amount <= 0.0 {
+        return Ok(());
+    }
+
+    let customer_id = customer_id.ok_or_else(|| "Sales on account need a customer".to_string())?;
+    let account = load_account(conn, customer_id)?;
+    if account.credit_limit <= 0.0 {
+        return Err(format!("{} has no credit account", account.customer_name));
+    }
+    if amount > account.available_credit + 0.005 {
+        return Err(format!("Credit limit exceeded: {:.2} available", account.available_credit));
+    }
+
+    insert_entry(conn, customer_id, Some(transaction_id), "charge", amount, None, None, None, None, user_id, timestamp)
+}
+
+// Cancel what a voided sale charged to an account
+pub fn reverse_sale(conn: &Connection, transaction_id: &str, note: &str, user_id: Option<&str>) -> Result<(), String> {
+    let mut stmt = conn.prepare(
+        "SELECT customer_id, SUM(amount) FROM ar_ledger
+         WHERE transaction_id = ?1
+         GROUP BY customer_id"
+    ).map_err(|e| e.to_string())?;
+
+    let charges = stmt.query_map(params![transaction_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let timestamp = Utc::now().to_rfc3339();
+    for (customer_id, amount) in charges {
+        if round_money(amount) != 0.0 {
+            insert_entry(conn, &customer_id, Some(transaction_id), "adjust", -amount, None, None, Some(note), None, user_id, &timestamp)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn map_entry(row: &rusqlite::Row) -> rusqlite::Result<ArEntry> {
+    Ok(ArEntry {
+        id: row.get(0)?,
+        customer_id: row.get(1)?,
+        transaction_id: row.get(2)?,
+        entry_type: row.get(3)?,
+        amount: row.get(4)?,
+        payment_method: row.get(5)?,
+        reference: row.get(6)?,
+        note: row.get(7)?,
+        shift_id: row.get(8)?,
+        user_id: row.get(9)?,
+        created_at: row.get(10)?,
+    })
+}
+
+const ENTRY_COLUMNS: &str = "id, customer_id, transaction_id, entry_type, amount, payment_method, reference, note, shift_id, user_id, created_at";
+
+fn load_entries(conn: &Connection, customer_id: &str) -> Result<Vec<ArEntry>, String> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM ar_ledger WHERE customer_id = ?1 ORDER BY created_at, rowid",
+        ENTRY_COLUMNS
+    )).map_err(|e| e.to_string())?;
+
+    let entry_iter = stmt.query_map(params![customer_id], map_entry).map_err(|e| e.to_string())?;
+
+    let mut entries = Vec::new();
+    for entry in entry_iter {
+        entries.push(entry.map_err(|e| e.to_string())?);
+    }
+
+    Ok(entries)
+}
+
+// Set how much a customer may owe; needs a manager's approval
+#[tauri::command]
+pub fn set_customer_credit_limit(customer_id: String, credit_limit: f64, approval: LoginData, db: State<Database>) -> Result<ArAccount, String> {
+    if credit_limit < 0.0 || !credit_limit.is_finite() {
+        return Err("Credit limit must be zero or more".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    crate::verify_manager_approval(&conn, &approval)?;
+
+    conn.execute(
+        "UPDATE customers SET credit_limit = ?1, updated_at = ?2 WHERE id = ?3",
+        params![round_money(credit_limit), Utc::now().to_rfc3339(), customer_id]
+    ).map_err(|e| e.to_string())?;
+
+    load_account(&conn, &customer_id)
+}
+
+#[tauri::command]
+pub fn get_customer_account(customer_id: String, db: State<Database>) -> Result<ArAccount, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    load_account(&conn, &customer_id)
+}
+
+// Take a payment towards an account. Drawer cash is posted to the shift.
+#[tauri::command]
+pub fn receive_account_payment(payment: ArPaymentInput, db: State<Database>) -> Result<ArAccount, String> {
+    let amount = round_money(payment.amount);
+    if amount <= 0.0 || !amount.is_finite() {
+        return Err("Payment amount must be positive".to_string());
+    }
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    let methods = crate::payments::load_payment_methods(&tx)?;
+    let method = methods.iter()
+        .find(|m| m.code == payment.payment_method && m.is_active)
+        .ok_or_else(|| format!("Unknown or inactive payment method: {}", payment.payment_method))?;
+    let stored_value = [ON_ACCOUNT_METHOD, crate::loyalty::POINTS_METHOD, crate::giftcards::GIFT_CARD_METHOD, crate::giftcards::STORE_CREDIT_METHOD];
+    if stored_value.contains(&method.code.as_str()) {
+        return Err(format!("Account payments cannot be made with {}", method.display_name));
+    }
+    if method.requires_reference && payment.reference.as_deref().map(str::trim).unwrap_or("").is_empty() {
+        return Err(format!("{} payments require a reference", method.display_name));
+    }
+
+    let account = load_account(&tx, &payment.customer_id)?;
+    if amount > account.balance + 0.005 {
+        return Err(format!("Payment exceeds the balance of {:.2}", account.balance));
+    }
+
+    let timestamp = Utc::now().to_rfc3339();
+    insert_entry(
+        &tx,
+        &payment.customer_id,
+        None,
+        "payment",
+        -amount,
+        Some(&method.code),
+        payment.reference.as_deref(),
+        None,
+        payment.shift_id.as_deref(),
+        Some(&payment.user_id),
+        &timestamp,
+    )?;
+
+    if let (true, Some(shift_id)) = (method.is_drawer_cash, &payment.shift_id) {
+        let status: String = tx.query_row(
+            "SELECT status FROM cash_shifts WHERE id = ?1",
+            params![shift_id],
+            |row| row.get(0)
+        ).map_err(|e| e.to_string())?;
+        if status != "open" {
+            return Err("Shift is not open".to_string());
+        }
+
+        tx.execute(
+            "INSERT INTO cash_movements (id, shift_id, movement_type, amount, reason, timestamp, user_id) VALUES (?1, ?2, 'cash_in', ?3, ?4, ?5, ?6)",
+            params![Uuid::new_v4().to_string(), shift_id, amount, format!("Account payment {}", account.customer_name), timestamp, payment.user_id]
+        ).map_err(|e| e.to_string())?;
+
+        tx.execute(
+            "UPDATE cash_shifts SET expected_cash = expected_cash + ?1 WHERE id = ?2",
+            params![amount, shift_id]
+        ).map_err(|e| e.to_string())?;
+    }
+
+    let account = load_account(&tx, &payment.customer_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(account)
+}
+
+// Aging of every account with a balance as of a date (default today), using
+// only entries made up to the end of that day. Charges are aged by sale date;
+// reversals clear their own sale and payments pay off the oldest charges first.
+#[tauri::command]
+pub fn get_ar_aging(as_of: Option<String>, db: State<Database>) -> Result<Vec<ArAging>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let as_of = match as_of {
+        Some(date) => NaiveDate::parse_from_str(&date, "%Y-%m-%d").map_err(|e| e.to_string())?,
+        None => Utc::now().date_naive(),
+    };
+
+    let customers = conn.prepare(
+        "SELECT c.id, c.name FROM customers c
+         WHERE EXISTS (SELECT 1 FROM ar_ledger l WHERE l.customer_id = c.id)
+         ORDER BY c.name"
+    ).map_err(|e| e.to_string())?
+    .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+    .map_err(|e| e.to_string())?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| e.to_string())?;
+
+    let mut aging = Vec::new();
+    for (customer_id, customer_name) in customers {
+        let entries: Vec<(ArEntry, i64)> = load_entries(&conn, &customer_id)?
+            .into_iter()
+            .map(|entry| {
+                let days = DateTime::parse_from_rfc3339(&entry.created_at)
+                    .map(|time| (as_of - time.with_timezone(&Utc).date_naive()).num_days())
+                    .unwrap_or(0);
+                (entry, days)
+            })
+            .filter(|(_, days)| *days >= 0)
+            .collect();
+
+        // Open amount left on each charge, oldest first
+        let mut charges: Vec<(&ArEntry, i64, f64)> = entries.iter()
+            .filter(|(e, _)| e.amount > 0.0)
+            .map(|(e, days)| (e, *days, e.amount))
+            .collect();
+
+        let mut credits = 0.0;
+        for (credit, _) in entries.iter().filter(|(e, _)| e.amount < 0.0) {
+            let mut remaining = -credit.amount;
+            if let Some(transaction_id) = &credit.transaction_id {
+                for (charge, _, open) in charges.iter_mut() {
+                    if charge.transaction_id.as_ref() == Some(transaction_id) {
+                        let settled = remaining.min(*open);
+                        *open -= settled;
+                        remaining -= settled;
+                    }
+                }
+            }
+            credits += remaining;
+        }
+
+        let mut row = ArAging {
+            customer_id,
+            customer_name,
+            balance: 0.0,
+            current: 0.0,
+            days_31_60: 0.0,
+            days_61_90: 0.0,
+            over_90: 0.0,
+        };
+
+        for (_, days, open) in charges {
+            let settled = credits.min(open);
+            credits -= settled;
+            let open = round_money(open - settled);
+            if open <= 0.0 {
+                continue;
+            }
+
+            let bucket = match days {
+                d if d <= 30 => &mut row.current,
+                d if d <= 60 => &mut row.days_31_60,
+                d if d <= 90 => &mut row.days_61_90,
+                _ => &mut row.over_90,
+            };
+            *bucket = round_money(*bucket + open);
+            row.balance = round_money(row.balance + open);
+        }
+
+        if row.balance > 0.0 {
+            aging.push(row);
+        }
+    }
+
+    Ok(aging)
+}
+
+// Account statement between two dates (`end_date` exclusive) with running balance
+#[tauri::command]
+pub fn get_customer_statement(customer_id: String, start_date: String, end_date: String, db: State<Database>) -> Result<ArStatement, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let account = load_account(&conn, &customer_id)?;
+
+    let entries = load_entries(&conn, &customer_id)?;
+    let opening_balance = round_money(entries.iter().filter(|e| e.created_at < start_date).map(|e| e.amount).sum());
+
+    let mut balance = opening_balance;
+    let mut lines = Vec::new();
+    for entry in entries.into_iter().filter(|e| e.created_at >= start_date && e.created_at < end_date) {
+        balance = round_money(balance + entry.amount);
+        lines.push(StatementLine { entry, balance });
+    }
+    let closing_balance = balance;
+
+    Ok(ArStatement {
+        account,
+        start_date,
+        end_date,
+        opening_balance,
+        lines,
+        closing_balance,
+    })
+}
@@ -0,0 +1,176 @@
+// Reorder points and purchase suggestions.
+//
+// Each product can carry a reorder point, a minimum reorder quantity and a
+// preferred supplier. A sale that takes stock from above the reorder point to
+// at or below it raises a `low-stock` event for the frontend. Suggestions
+// compare stock plus quantities still on order with the reorder point and
+// recent sales velocity.
+
+use chrono::{Duration, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::Database;
+
+pub const LOW_STOCK_EVENT: &str = "low-stock";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LowStockAlert {
+    pub product_id: String,
+    pub product_name: String,
+    pub stock: i32,
+    pub reorder_point: i32,
+    pub reorder_quantity: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReorderSuggestion {
+    pub product_id: String,
+    pub product_name: String,
+    pub stock: i32,
+    pub on_order: i32,
+    pub reorder_point: i32,
+    pub reorder_quantity: i32,
+    pub daily_velocity: f64,
+    // Days until the stock on hand runs out at the current velocity
+    pub days_of_stock: Option<f64>,
+    pub suggested_quantity: i32,
+    pub unit_cost: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplierReorder {
+    // None for products with no preferred supplier and no purchase history
+    pub supplier_id: Option<String>,
+    pub supplier_name: String,
+    pub suggestions: Vec<ReorderSuggestion>,
+    pub estimated_cost: f64,
+}
+
+// The alert for a sale that moved a product's stock across its reorder point
+pub fn low_stock_alert(conn: &Connection, product_id: &str, stock_before: i32, stock_after: i32) -> Result<Option<LowStockAlert>, String> {
+    let (product_name, reorder_point, reorder_quantity): (String, i32, i32) = conn.query_row(
+        "SELECT name, reorder_point, reorder_quantity FROM products WHERE id = ?1",
+        params![product_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+    ).map_err(|e| e.to_string())?;
+
+    if reorder_point <= 0 || stock_before <= reorder_point || stock_after > reorder_point {
+        return Ok(None);
+    }
+
+    Ok(Some(LowStockAlert {
+        product_id: product_id.to_string(),
+        product_name,
+        stock: stock_after,
+        reorder_point,
+        reorder_quantity,
+    }))
+}
+
+#[tauri::command]
+pub fn set_product_reorder(product_id: String, reorder_point: i32, reorder_quantity: i32, supplier_id: Option<String>, db: State<Database>) -> Result<(), String> {
+    if reorder_point < 0 || reorder_quantity < 0 {
+        return Err("Reorder point and quantity cannot be negative".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let updated = conn.execute(
+        "UPDATE products SET reorder_point = ?1, reorder_quantity = ?2, supplier_id = ?3 WHERE id = ?4",
+        params![reorder_point, reorder_quantity, supplier_id.filter(|s| !s.is_empty()), product_id]
+    ).map_err(|e| e.to_string())?;
+
+    if updated == 0 {
+        return Err(format!("Product {} not found", product_id));
+    }
+
+    Ok(())
+}
+
+// Products at or below their reorder point once open orders arrive, grouped by
+// supplier. The suggestion tops stock up to the reorder point plus the expected
+// sales over `reorder_cover_days`, and is never less than the reorder quantity.
+// Velocity is the average daily quantity sold over the last `window_days`
+// (default: the `reorder_velocity_days` setting).
+#[tauri::command]
+pub fn get_reorder_suggestions(window_days: Option<i64>, supplier_id: Option<String>, db: State<Database>) -> Result<Vec<SupplierReorder>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let window_days = match window_days {
+        Some(days) => days,
+        None => crate::setting_f64(&conn, "reorder_velocity_days", 30.0)? as i64,
+    }.clamp(1, 3650);
+    let cover_days = crate::setting_f64(&conn, "reorder_cover_days", 14.0)?.max(0.0);
+    let since = (Utc::now() - Duration::days(window_days)).to_rfc3339();
+
+    // Preferred supplier, else whoever the product was last ordered from
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.name, p.stock, p.reorder_point, p.reorder_quantity, p.cost_price,
+                COALESCE((SELECT SUM(l.quantity_ordered - l.quantity_received)
+                          FROM purchase_order_lines l JOIN purchase_orders po ON po.id = l.purchase_order_id
+                          WHERE l.product_id = p.id AND po.status IN ('open', 'partially_received')), 0),
+                COALESCE((SELECT SUM(ti.stock_units)
+                          FROM transaction_items ti JOIN transactions t ON t.id = ti.transaction_id
+                          WHERE ti.product_id = p.id AND t.voided_at IS NULL AND t.timestamp >= ?1), 0),
+                s.id, s.name
+         FROM products p
+         LEFT JOIN suppliers s ON s.id = COALESCE(p.supplier_id, (
+             SELECT po.supplier_id FROM purchase_order_lines l JOIN purchase_orders po ON po.id = l.purchase_order_id
+             WHERE l.product_id = p.id ORDER BY po.created_at DESC LIMIT 1))
+         WHERE p.reorder_point > 0 AND (?2 IS NULL OR s.id = ?2)
+         ORDER BY s.name, p.name"
+    ).map_err(|e| e.to_string())?;
+
+    let row_iter = stmt.query_map(params![since, supplier_id], |row| {
+        let stock: i32 = row.get(2)?;
+        let reorder_point: i32 = row.get(3)?;
+        let reorder_quantity: i32 = row.get(4)?;
+        let on_order: i32 = row.get(6)?;
+        let sold: i64 = row.get(7)?;
+        let daily_velocity = (sold as f64 / window_days as f64 * 100.0).round() / 100.0;
+        let target = reorder_point + (sold as f64 / window_days as f64 * cover_days).ceil() as i32;
+        Ok((
+            ReorderSuggestion {
+                product_id: row.get(0)?,
+                product_name: row.get(1)?,
+                stock,
+                on_order,
+                reorder_point,
+                reorder_quantity,
+                daily_velocity,
+                days_of_stock: if sold > 0 { Some((stock.max(0) as f64 * window_days as f64 / sold as f64 * 10.0).round() / 10.0) } else { None },
+                suggested_quantity: (target - stock - on_order).max(reorder_quantity),
+                unit_cost: row.get(5)?,
+            },
+            row.get::<_, Option<String>>(8)?,
+            row.get::<_, Option<String>>(9)?,
+        ))
+    }).map_err(|e| e.to_string())?;
+
+    let mut suppliers: Vec<SupplierReorder> = Vec::new();
+    for row in row_iter {
+        let (suggestion, supplier_id, supplier_name) = row.map_err(|e| e.to_string())?;
+        if suggestion.stock + suggestion.on_order > suggestion.reorder_point || suggestion.suggested_quantity <= 0 {
+            continue;
+        }
+
+        let index = match suppliers.iter().position(|s| s.supplier_id == supplier_id) {
+            Some(index) => index,
+            None => {
+                suppliers.push(SupplierReorder {
+                    supplier_id,
+                    supplier_name: supplier_name.unwrap_or_else(|| "No supplier".to_string()),
+                    suggestions: Vec::new(),
+                    estimated_cost: 0.0,
+                });
+                suppliers.len() - 1
+            }
+        };
+        let supplier = &mut suppliers[index];
+        supplier.estimated_cost = crate::pricing::round_money(supplier.estimated_cost + suggestion.suggested_quantity as f64 * suggestion.unit_cost);
+        supplier.suggestions.push(suggestion);
+    }
+
+    Ok(suppliers)
+}
@@ -0,0 +1,216 @@
+// Weighed products and in-store scale labels.
+//
+// Products sold by weight are priced per kg and sold in decimal quantities
+// (kg). Their stock is kept in grams so that the inventory ledger stays in
+// whole units; purchase orders and cost price count grams as well.
+//
+// Scale labels are EAN-13 codes starting with an in-store prefix that carry
+// the product's PLU and either the weight or the price; the digit layout comes
+// from the `scale_label_layouts` setting. Label prefixes must not overlap the
+// prefix of generated in-store barcodes (see barcodes.rs).
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::Database;
+
+// Grams of stock per kg sold
+pub const GRAMS_PER_KG: f64 = 1000.0;
+
+// Where PLU and value sit in a scale label. Positions are 0-based digit
+// offsets into the 13-digit code; `value_decimals` places the decimal point
+// (3 for grams in a kg weight, 0 for whole currency prices).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScaleLabelLayout {
+    pub prefix: String,
+    pub plu_start: usize,
+    pub plu_length: usize,
+    pub value_start: usize,
+    pub value_length: usize,
+    // "weight" or "price"
+    pub value_type: String,
+    pub value_decimals: u32,
+}
+
+// A decoded scale label
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScaleLabel {
+    pub plu: u64,
+    pub value_type: String,
+    pub value: f64,
+}
+
+// Weight labels on 20 to 28; 29 is kept for generated in-store barcodes
+pub fn default_layouts() -> Vec<ScaleLabelLayout> {
+    (20..=28)
+        .map(|prefix| ScaleLabelLayout {
+            prefix: prefix.to_string(),
+            plu_start: 2,
+            plu_length: 5,
+            value_start: 7,
+            value_length: 5,
+            value_type: "weight".to_string(),
+            value_decimals: 3,
+        })
+        .collect()
+}
+
+// Scale label prefixes and the in-store barcode prefix must be disjoint, or a
+// generated barcode could be read as a scale label
+pub fn check_prefixes(layouts: &[ScaleLabelLayout], internal_prefix: &str) -> Result<(), String> {
+    for layout in layouts {
+        if layout.prefix.is_empty() || !layout.prefix.chars().all(|c| c.is_ascii_digit()) {
+            return Err(format!("Scale label prefix {} must be numeric", layout.prefix));
+        }
+        if layout.prefix.starts_with(internal_prefix) || internal_prefix.starts_with(&layout.prefix) {
+            return Err(format!(
+                "Scale label prefix {} overlaps the internal barcode prefix {}",
+                layout.prefix, internal_prefix
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn scale_label_layouts(conn: &Connection) -> Result<Vec<ScaleLabelLayout>, String> {
+    Ok(crate::get_setting(conn, "scale_label_layouts")?
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_else(default_layouts))
+}
+
+// Decode a scanned code with the first layout whose prefix it starts with.
+// Codes that are not valid EAN-13 or match no layout are not scale labels.
+pub fn parse_scale_label(barcode: &str, layouts: &[ScaleLabelLayout]) -> Option<ScaleLabel> {
+    if !crate::barcodes::is_valid_ean13(barcode) {
+        return None;
+    }
+
+    let layout = layouts.iter().find(|l| !l.prefix.is_empty() && barcode.starts_with(&l.prefix))?;
+    let digits = |start: usize, length: usize| -> Option<u64> {
+        if length == 0 || start + length > 12 {
+            return None;
+        }
+        barcode[start..start + length].parse().ok()
+    };
+
+    Some(ScaleLabel {
+        plu: digits(layout.plu_start, layout.plu_length)?,
+        value_type: layout.value_type.clone(),
+        value: digits(layout.value_start, layout.value_length)? as f64 / 10f64.powi(layout.value_decimals as i32),
+    })
+}
+
+// A scale label as a sale line: the kg it takes from stock and, for price
+// labels, the amount printed on it, which is charged as is
+#[derive(Debug, Clone, Copy)]
+pub struct ScaleLine {
+    pub quantity: f64,
+    pub amount: Option<f64>,
+}
+
+// Turn a label into a sale line. Price labels are turned back into a weight at
+// the product's price per kg; that weight is only used for stock.
+pub fn scale_line(label: &ScaleLabel, name: &str, price_per_kg: f64) -> Result<ScaleLine, String> {
+    let (kg, amount) = match label.value_type.as_str() {
+        "price" if price_per_kg > 0.0 => (label.value / price_per_kg, Some(label.value)),
+        "price" => return Err(format!("{} has no price per kg", name)),
+        _ => (label.value, None),
+    };
+
+    Ok(ScaleLine {
+        quantity: (kg * GRAMS_PER_KG).round() / GRAMS_PER_KG,
+        amount,
+    })
+}
+
+// The sale line for a product scanned with `barcode`, if that is a scale label
+// carrying the product's PLU
+pub fn label_line(conn: &Connection, product_id: &str, barcode: &str) -> Result<Option<ScaleLine>, String> {
+    let label = match parse_scale_label(barcode, &scale_label_layouts(conn)?) {
+        Some(label) => label,
+        None => return Ok(None),
+    };
+    let (name, price, sold_by_weight, plu): (String, f64, bool, Option<String>) = conn.query_row(
+        "SELECT name, price, sold_by_weight, plu FROM products WHERE id = ?1",
+        params![product_id],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+    ).map_err(|_| format!("Product {} not found", product_id))?;
+
+    if !sold_by_weight || plu.and_then(|plu| plu.parse::<u64>().ok()) != Some(label.plu) {
+        return Ok(None);
+    }
+
+    scale_line(&label, &name, price).map(Some)
+}
+
+// Units of stock taken by selling `quantity` of a product: grams for weighed
+// products, otherwise whole items times the pack size
+pub fn stock_units(conn: &Connection, product_id: &str, quantity: f64, pack_quantity: i32) -> Result<i32, String> {
+    let sold_by_weight: bool = conn.query_row(
+        "SELECT sold_by_weight FROM products WHERE id = ?1",
+        params![product_id],
+        |row| row.get(0)
+    ).map_err(|_| format!("Product {} not found", product_id))?;
+
+    if !quantity.is_finite() || quantity <= 0.0 {
+        return Err("Quantity must be greater than zero".to_string());
+    }
+    if sold_by_weight {
+        return Ok((quantity * GRAMS_PER_KG).round() as i32);
+    }
+    if quantity.fract() != 0.0 {
+        return Err("Only products sold by weight can have a decimal quantity".to_string());
+    }
+
+    Ok(quantity as i32 * pack_quantity)
+}
+
+// Mark a product as sold by weight (its price is then per kg) and set the PLU
+// the scales print on its labels
+#[tauri::command]
+pub fn set_product_scale(product_id: String, sold_by_weight: bool, plu: Option<String>, db: State<Database>) -> Result<(), String> {
+    let plu = plu.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+    if let Some(plu) = &plu {
+        if !plu.chars().all(|c| c.is_ascii_digit()) {
+            return Err("PLU must be numeric".to_string());
+        }
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    if let Some(plu) = &plu {
+        let taken: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM products WHERE CAST(plu AS INTEGER) = CAST(?1 AS INTEGER) AND id != ?2)",
+            params![plu, product_id],
+            |row| row.get(0)
+        ).map_err(|e| e.to_string())?;
+        if taken {
+            return Err(format!("PLU {} is already in use", plu));
+        }
+    }
+
+    // Stock switches between items and grams, so only an empty product can change over
+    let (current, stock): (bool, i32) = conn.query_row(
+        "SELECT sold_by_weight, stock FROM products WHERE id = ?1",
+        params![product_id],
+        |row| Ok((row.get(0)?, row.get(1)?))
+    ).map_err(|_| format!("Product {} not found", product_id))?;
+    if current != sold_by_weight && stock != 0 {
+        return Err("Stock must be zero before changing whether a product is sold by weight".to_string());
+    }
+
+    conn.execute(
+        "UPDATE products SET sold_by_weight = ?1, plu = ?2 WHERE id = ?3",
+        params![sold_by_weight, plu, product_id]
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_scale_label_layouts(db: State<Database>) -> Result<Vec<ScaleLabelLayout>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    scale_label_layouts(&conn)
+}
@@ -0,0 +1,317 @@
+// Stocktake (physical inventory count) sessions.
+//
+// Opening a stocktake snapshots the system stock of every product in scope,
+// and the first count of a product takes its snapshot again, so the snapshot
+// is the stock on the books when its shelf was counted. Counters scan barcodes
+// and enter quantities; several entries for the same product (different
+// shelves or counters) add up. Variance is counted minus snapshot, so sales
+// made while the count is running do not show up as shrinkage: approval posts
+// the variance as a 'count' movement on top of the current stock.
+
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::{Database, LoginData};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Stocktake {
+    pub id: String,
+    pub name: String,
+    // Only products of this category are counted; None counts everything
+    pub category: Option<String>,
+    pub status: String,
+    pub created_by: String,
+    pub created_at: String,
+    pub approved_by: Option<String>,
+    pub approved_at: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StocktakeLine {
+    pub product_id: String,
+    pub product_name: String,
+    pub barcode: String,
+    // System stock at the product's first count, or at the start of the
+    // stocktake while it is uncounted
+    pub snapshot_stock: i32,
+    // None until the product has been counted
+    pub counted_quantity: Option<i32>,
+    pub count_entries: i64,
+    pub variance: i32,
+    pub variance_value: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StocktakeReport {
+    pub stocktake: Stocktake,
+    pub lines: Vec<StocktakeLine>,
+    pub counted_products: i64,
+    pub uncounted_products: i64,
+    pub total_variance: i32,
+    pub total_variance_value: f64,
+}
+
+const STOCKTAKE_COLUMNS: &str = "id, name, category, status, created_by, created_at, approved_by, approved_at";
+
+fn map_stocktake(row: &rusqlite::Row) -> rusqlite::Result<Stocktake> {
+    Ok(Stocktake {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        category: row.get(2)?,
+        status: row.get(3)?,
+        created_by: row.get(4)?,
+        created_at: row.get(5)?,
+        approved_by: row.get(6)?,
+        approved_at: row.get(7)?,
+    })
+}
+
+fn find_stocktake(conn: &Connection, stocktake_id: &str) -> Result<Stocktake, String> {
+    match conn.query_row(
+        &format!("SELECT {} FROM stocktakes WHERE id = ?1", STOCKTAKE_COLUMNS),
+        params![stocktake_id],
+        map_stocktake
+    ) {
+        Ok(stocktake) => Ok(stocktake),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Err("Stocktake not found".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn find_open_stocktake(conn: &Connection, stocktake_id: &str) -> Result<Stocktake, String> {
+    let stocktake = find_stocktake(conn, stocktake_id)?;
+    if stocktake.status != "open" {
+        return Err(format!("Stocktake is {}", stocktake.status));
+    }
+    Ok(stocktake)
+}
+
+// Snapshot and counted totals per product. Uncounted products are treated as
+// unchanged unless `zero_uncounted` is set, which writes off their current stock.
+fn load_lines(conn: &Connection, stocktake_id: &str, zero_uncounted: bool) -> Result<Vec<StocktakeLine>, String> {
+    let mut stmt = conn.prepare(
+        "SELECT l.product_id, p.name, p.barcode, l.snapshot_stock, p.price, p.stock,
+                (SELECT SUM(c.quantity) FROM stocktake_counts c WHERE c.stocktake_id = l.stocktake_id AND c.product_id = l.product_id),
+                (SELECT COUNT(*) FROM stocktake_counts c WHERE c.stocktake_id = l.stocktake_id AND c.product_id = l.product_id)
+         FROM stocktake_lines l
+         JOIN products p ON p.id = l.product_id
+         WHERE l.stocktake_id = ?1
+         ORDER BY p.name"
+    ).map_err(|e| e.to_string())?;
+
+    let line_iter = stmt.query_map(params![stocktake_id], |row| {
+        let snapshot_stock: i32 = row.get(3)?;
+        let price: f64 = row.get(4)?;
+        let stock: i32 = row.get(5)?;
+        let counted_quantity: Option<i32> = row.get(6)?;
+        let variance = match counted_quantity {
+            Some(counted) => counted - snapshot_stock,
+            None if zero_uncounted => -stock,
+            None => 0,
+        };
+        Ok(StocktakeLine {
+            product_id: row.get(0)?,
+            product_name: row.get(1)?,
+            barcode: row.get(2)?,
+            snapshot_stock,
+            counted_quantity,
+            count_entries: row.get(7)?,
+            variance,
+            variance_value: crate::pricing::round_money(variance as f64 * price),
+        })
+    }).map_err(|e| e.to_string())?;
+
+    let mut lines = Vec::new();
+    for line in line_iter {
+        lines.push(line.map_err(|e| e.to_string())?);
+    }
+
+    Ok(lines)
+}
+
+fn build_report(stocktake: Stocktake, lines: Vec<StocktakeLine>) -> StocktakeReport {
+    let counted_products = lines.iter().filter(|l| l.counted_quantity.is_some()).count() as i64;
+    StocktakeReport {
+        stocktake,
+        counted_products,
+        uncounted_products: lines.len() as i64 - counted_products,
+        total_variance: lines.iter().map(|l| l.variance).sum(),
+        total_variance_value: crate::pricing::round_money(lines.iter().map(|l| l.variance_value).sum()),
+        lines,
+    }
+}
+
+// Open a count and snapshot the stock of the products it covers. Two open
+// stocktakes may not cover the same products.
+#[tauri::command]
+pub fn start_stocktake(name: Option<String>, category: Option<String>, user_id: String, db: State<Database>) -> Result<Stocktake, String> {
+    let category = category.map(|c| c.trim().to_string()).filter(|c| !c.is_empty());
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    let overlapping: i64 = tx.query_row(
+        "SELECT COUNT(*) FROM stocktakes WHERE status = 'open' AND (category IS NULL OR ?1 IS NULL OR category = ?1)",
+        params![category],
+        |row| row.get(0)
+    ).map_err(|e| e.to_string())?;
+    if overlapping > 0 {
+        return Err("Another stocktake covering these products is still open".to_string());
+    }
+
+    let stocktake_id = Uuid::new_v4().to_string();
+    let now = Utc::now().to_rfc3339();
+    let name = name
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .unwrap_or_else(|| format!("Stocktake {}", Utc::now().format("%Y-%m-%d")));
+
+    tx.execute(
+        "INSERT INTO stocktakes (id, name, category, status, created_by, created_at) VALUES (?1, ?2, ?3, 'open', ?4, ?5)",
+        params![stocktake_id, name, category, user_id, now]
+    ).map_err(|e| e.to_string())?;
+
+    let snapshotted = tx.execute(
+        "INSERT INTO stocktake_lines (stocktake_id, product_id, snapshot_stock)
+         SELECT ?1, id, stock FROM products WHERE ?2 IS NULL OR category = ?2",
+        params![stocktake_id, category]
+    ).map_err(|e| e.to_string())?;
+    if snapshotted == 0 {
+        return Err("No products to count".to_string());
+    }
+
+    let stocktake = find_stocktake(&tx, &stocktake_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(stocktake)
+}
+
+#[tauri::command]
+pub fn list_stocktakes(status: Option<String>, db: State<Database>) -> Result<Vec<Stocktake>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM stocktakes WHERE ?1 IS NULL OR status = ?1 ORDER BY created_at DESC",
+        STOCKTAKE_COLUMNS
+    )).map_err(|e| e.to_string())?;
+
+    let stocktake_iter = stmt.query_map(params![status], map_stocktake).map_err(|e| e.to_string())?;
+
+    let mut stocktakes = Vec::new();
+    for stocktake in stocktake_iter {
+        stocktakes.push(stocktake.map_err(|e| e.to_string())?);
+    }
+
+    Ok(stocktakes)
+}
+
+// Add a scanned count. Entries for the same product add up; a negative
+// quantity corrects an earlier entry. Alternate and pack barcodes count as
+// their product, `quantity` packs at a time.
+#[tauri::command]
+pub fn record_stocktake_count(stocktake_id: String, barcode: String, quantity: i32, user_id: String, db: State<Database>) -> Result<StocktakeLine, String> {
+    if quantity == 0 {
+        return Err("Quantity must not be zero".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    find_open_stocktake(&conn, &stocktake_id)?;
+    let barcode = barcode.trim();
+
+    let (product_id, pack_quantity): (String, i32) = match crate::barcodes::find_barcode(&conn, barcode)? {
+        Some(pack) => (pack.product_id, pack.pack_quantity),
+        None => match conn.query_row("SELECT id FROM products WHERE barcode = ?1", params![barcode], |row| row.get(0)) {
+            Ok(product_id) => (product_id, 1),
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Err(format!("Barcode {} is not part of this stocktake", barcode)),
+            Err(e) => return Err(e.to_string()),
+        },
+    };
+    let in_stocktake: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM stocktake_lines WHERE stocktake_id = ?1 AND product_id = ?2)",
+        params![stocktake_id, product_id],
+        |row| row.get(0)
+    ).map_err(|e| e.to_string())?;
+    if !in_stocktake {
+        return Err(format!("Barcode {} is not part of this stocktake", barcode));
+    }
+    let quantity = quantity.checked_mul(pack_quantity).ok_or_else(|| "Quantity is too large".to_string())?;
+
+    let (counted, entries): (i32, i64) = conn.query_row(
+        "SELECT COALESCE(SUM(quantity), 0), COUNT(*) FROM stocktake_counts WHERE stocktake_id = ?1 AND product_id = ?2",
+        params![stocktake_id, product_id],
+        |row| Ok((row.get(0)?, row.get(1)?))
+    ).map_err(|e| e.to_string())?;
+    if counted + quantity < 0 {
+        return Err("Counted quantity cannot go below zero".to_string());
+    }
+
+    // Compare the count with the books as they stand now, not at the start of
+    // the stocktake, so sales since then are not counted as missing
+    if entries == 0 {
+        conn.execute(
+            "UPDATE stocktake_lines SET snapshot_stock = (SELECT stock FROM products WHERE id = ?2)
+             WHERE stocktake_id = ?1 AND product_id = ?2",
+            params![stocktake_id, product_id]
+        ).map_err(|e| e.to_string())?;
+    }
+
+    conn.execute(
+        "INSERT INTO stocktake_counts (id, stocktake_id, product_id, quantity, user_id, counted_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![Uuid::new_v4().to_string(), stocktake_id, product_id, quantity, user_id, Utc::now().to_rfc3339()]
+    ).map_err(|e| e.to_string())?;
+
+    load_lines(&conn, &stocktake_id, false)?
+        .into_iter()
+        .find(|l| l.product_id == product_id)
+        .ok_or_else(|| "Stocktake line not found".to_string())
+}
+
+// Variance of every product in the count against its snapshot
+#[tauri::command]
+pub fn get_stocktake(stocktake_id: String, zero_uncounted: Option<bool>, db: State<Database>) -> Result<StocktakeReport, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let stocktake = find_stocktake(&conn, &stocktake_id)?;
+    let lines = load_lines(&conn, &stocktake_id, zero_uncounted.unwrap_or(false))?;
+
+    Ok(build_report(stocktake, lines))
+}
+
+// Close the count and post each variance as a 'count' movement, all or nothing.
+// With `zero_uncounted` products nobody counted are written down to zero.
+#[tauri::command]
+pub fn approve_stocktake(stocktake_id: String, approval: LoginData, zero_uncounted: Option<bool>, db: State<Database>) -> Result<StocktakeReport, String> {
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let approved_by = crate::verify_manager_approval(&tx, &approval)?;
+    let stocktake = find_open_stocktake(&tx, &stocktake_id)?;
+    let lines = load_lines(&tx, &stocktake_id, zero_uncounted.unwrap_or(false))?;
+
+    for line in lines.iter().filter(|l| l.variance != 0) {
+        crate::inventory::record_movement(&tx, &line.product_id, "count", line.variance, Some("stocktake"), Some(&stocktake_id), Some(&approved_by), Some(&stocktake.name))?;
+    }
+
+    tx.execute(
+        "UPDATE stocktakes SET status = 'approved', approved_by = ?1, approved_at = ?2 WHERE id = ?3",
+        params![approved_by, Utc::now().to_rfc3339(), stocktake_id]
+    ).map_err(|e| e.to_string())?;
+
+    let stocktake = find_stocktake(&tx, &stocktake_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(build_report(stocktake, lines))
+}
+
+// Abandon an open count; stock is left as it is
+#[tauri::command]
+pub fn cancel_stocktake(stocktake_id: String, db: State<Database>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    find_open_stocktake(&conn, &stocktake_id)?;
+
+    conn.execute(
+        "UPDATE stocktakes SET status = 'cancelled' WHERE id = ?1",
+        params![stocktake_id]
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
@@ -0,0 +1,166 @@
+// Tax engine for PPN.
+//
+// Every product has a tax class (standard, reduced, exempt, ...) with a rate in
+// `tax_classes`. Shelf prices are either tax-inclusive or tax-exclusive, chosen
+// by the `tax_price_mode` setting.
+//
+// Tax is computed per transaction line on the line's amount after all
+// discounts, including its share of any cart-level discount (shares are
+// proportional to line amounts, with the rounding remainder on the last line).
+// Each line's tax is rounded to 2 decimals, halves away from zero, and the
+// transaction's tax is the sum of the rounded line taxes.
+//   inclusive: tax = amount * rate / (1 + rate); the amount already includes it
+//   exclusive: tax = amount * rate; the tax is added on top of the amount
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::pricing::round_money;
+use crate::Database;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TaxClass {
+    pub code: String,
+    pub name: String,
+    pub rate: f64,
+    pub is_active: bool,
+}
+
+// Tax computed for one line
+#[derive(Debug, Clone, Copy)]
+pub struct LineTax {
+    // Amount excluding tax (DPP)
+    pub taxable_amount: f64,
+    pub tax_amount: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PriceMode {
+    Inclusive,
+    Exclusive,
+}
+
+impl PriceMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PriceMode::Inclusive => "inclusive",
+            PriceMode::Exclusive => "exclusive",
+        }
+    }
+}
+
+pub fn price_mode(conn: &Connection) -> Result<PriceMode, String> {
+    match crate::get_setting(conn, "tax_price_mode")?.as_ref().and_then(|v| v.as_str()) {
+        Some("exclusive") => Ok(PriceMode::Exclusive),
+        _ => Ok(PriceMode::Inclusive),
+    }
+}
+
+pub fn line_tax(amount: f64, rate: f64, mode: PriceMode) -> LineTax {
+    match mode {
+        PriceMode::Inclusive => {
+            let tax_amount = round_money(amount * rate / (1.0 + rate));
+            LineTax { taxable_amount: round_money(amount - tax_amount), tax_amount }
+        }
+        PriceMode::Exclusive => LineTax { taxable_amount: round_money(amount), tax_amount: round_money(amount * rate) },
+    }
+}
+
+// Split a cart-level discount over lines in proportion to their amounts
+pub fn allocate_discount(discount: f64, line_amounts: &[f64]) -> Vec<f64> {
+    let total: f64 = line_amounts.iter().sum();
+    if discount <= 0.0 || total <= 0.0 {
+        return vec![0.0; line_amounts.len()];
+    }
+
+    let mut shares = Vec::with_capacity(line_amounts.len());
+    let mut given = 0.0;
+    for (index, amount) in line_amounts.iter().enumerate() {
+        let share = if index + 1 == line_amounts.len() {
+            round_money(discount - given)
+        } else {
+            round_money(discount * amount / total)
+        };
+        given += share;
+        shares.push(share);
+    }
+    shares
+}
+
+// Tax class code and rate for a product; unknown products and classes are untaxed
+pub fn product_tax_rate(conn: &Connection, product_id: &str) -> Result<(String, f64), String> {
+    match conn.query_row(
+        "SELECT p.tax_class, COALESCE(tc.rate, 0.0)
+         FROM products p
+         LEFT JOIN tax_classes tc ON tc.code = p.tax_class
+         WHERE p.id = ?1",
+        params![product_id],
+        |row| Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?)),
+    ) {
+        Ok(rate) => Ok(rate),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(("exempt".to_string(), 0.0)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn get_tax_classes(db: State<Database>) -> Result<Vec<TaxClass>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare("SELECT code, name, rate, is_active FROM tax_classes ORDER BY code").map_err(|e| e.to_string())?;
+
+    let class_iter = stmt.query_map([], |row| {
+        Ok(TaxClass {
+            code: row.get(0)?,
+            name: row.get(1)?,
+            rate: row.get(2)?,
+            is_active: row.get(3)?,
+        })
+    }).map_err(|e| e.to_string())?;
+
+    let mut classes = Vec::new();
+    for class in class_iter {
+        classes.push(class.map_err(|e| e.to_string())?);
+    }
+
+    Ok(classes)
+}
+
+// Create or update a tax class; `rate` is a fraction (0.11 for 11%)
+#[tauri::command]
+pub fn save_tax_class(tax_class: TaxClass, db: State<Database>) -> Result<(), String> {
+    if !(0.0..1.0).contains(&tax_class.rate) {
+        return Err("Tax rate must be a fraction between 0 and 1".to_string());
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "INSERT INTO tax_classes (code, name, rate, is_active) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(code) DO UPDATE SET name = excluded.name, rate = excluded.rate, is_active = excluded.is_active",
+        params![tax_class.code, tax_class.name, tax_class.rate, tax_class.is_active]
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_product_tax_class(product_id: String, tax_class: String, db: State<Database>) -> Result<(), String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let exists: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM tax_classes WHERE code = ?1 AND is_active = 1",
+        params![tax_class],
+        |row| row.get(0)
+    ).map_err(|e| e.to_string())?;
+
+    if !exists {
+        return Err(format!("Unknown tax class: {}", tax_class));
+    }
+
+    conn.execute(
+        "UPDATE products SET tax_class = ?1 WHERE id = ?2",
+        params![tax_class, product_id]
+    ).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
@@ -0,0 +1,342 @@
+// Product variants.
+//
+// A parent product defines attributes (e.g. Size: S/M/L, Colour: Red/Blue) and
+// groups variant SKUs. Each variant is an ordinary row in `products` with its
+// own barcode and stock, so selling, stock movements and stocktakes work on it
+// unchanged. A variant without a price override follows the parent's base price.
+
+use std::collections::BTreeMap;
+
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use uuid::Uuid;
+
+use crate::{Database, Product};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariantAttribute {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ParentProduct {
+    pub id: String,
+    pub name: String,
+    pub category: String,
+    pub base_price: f64,
+    pub tax_class: String,
+    pub attributes: Vec<VariantAttribute>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ParentProductInput {
+    pub name: String,
+    pub category: String,
+    pub base_price: f64,
+    pub tax_class: Option<String>,
+    pub attributes: Vec<VariantAttribute>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VariantInput {
+    // One value for each of the parent's attributes, e.g. {"Size": "M"}
+    pub attributes: BTreeMap<String, String>,
+    // Left empty to generate an in-store barcode
+    #[serde(default)]
+    pub barcode: String,
+    pub price_override: Option<f64>,
+    #[serde(default)]
+    pub stock: i32,
+    pub user_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ParentProductDetail {
+    pub parent: ParentProduct,
+    pub variants: Vec<Product>,
+    pub total_stock: i32,
+}
+
+const PARENT_COLUMNS: &str = "id, name, category, base_price, tax_class, attributes, created_at, updated_at";
+
+fn map_parent(row: &rusqlite::Row) -> rusqlite::Result<(ParentProduct, String)> {
+    Ok((
+        ParentProduct {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            category: row.get(2)?,
+            base_price: row.get(3)?,
+            tax_class: row.get(4)?,
+            attributes: Vec::new(),
+            created_at: row.get(6)?,
+            updated_at: row.get(7)?,
+        },
+        row.get(5)?,
+    ))
+}
+
+// Attribute definitions are stored as JSON; decode them into the parent
+fn decode_parent((mut parent, attributes): (ParentProduct, String)) -> Result<ParentProduct, String> {
+    parent.attributes = serde_json::from_str(&attributes).map_err(|e| e.to_string())?;
+    Ok(parent)
+}
+
+pub fn find_parent(conn: &Connection, parent_id: &str) -> Result<ParentProduct, String> {
+    match conn.query_row(
+        &format!("SELECT {} FROM parent_products WHERE id = ?1", PARENT_COLUMNS),
+        params![parent_id],
+        map_parent
+    ) {
+        Ok(parent) => decode_parent(parent),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Err("Parent product not found".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn validate_parent(parent: &ParentProductInput) -> Result<(), String> {
+    if parent.name.trim().is_empty() {
+        return Err("Product name is required".to_string());
+    }
+    if parent.category.trim().is_empty() {
+        return Err("Category is required".to_string());
+    }
+    if !parent.base_price.is_finite() || parent.base_price < 0.0 {
+        return Err("Price cannot be negative".to_string());
+    }
+    if parent.attributes.is_empty() {
+        return Err("At least one attribute is required".to_string());
+    }
+    for (index, attribute) in parent.attributes.iter().enumerate() {
+        if attribute.name.trim().is_empty() || attribute.values.is_empty() {
+            return Err("Every attribute needs a name and at least one value".to_string());
+        }
+        if parent.attributes[..index].iter().any(|a| a.name.trim() == attribute.name.trim()) {
+            return Err(format!("Attribute {} is defined twice", attribute.name.trim()));
+        }
+    }
+    Ok(())
+}
+
+fn clean_attributes(attributes: &[VariantAttribute]) -> Vec<VariantAttribute> {
+    attributes.iter().map(|a| VariantAttribute {
+        name: a.name.trim().to_string(),
+        values: a.values.iter().map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect(),
+    }).collect()
+}
+
+// Variant name from the parent name and attribute values in definition order,
+// e.g. "Polo Shirt - M / Red"
+fn variant_name(parent: &ParentProduct, attributes: &BTreeMap<String, String>) -> String {
+    let values: Vec<&str> = parent.attributes.iter()
+        .filter_map(|a| attributes.get(&a.name).map(|v| v.as_str()))
+        .collect();
+    format!("{} - {}", parent.name, values.join(" / "))
+}
+
+fn load_variants(conn: &Connection, parent_id: &str) -> Result<Vec<Product>, String> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM products WHERE parent_id = ?1 ORDER BY name",
+        crate::PRODUCT_COLUMNS
+    )).map_err(|e| e.to_string())?;
+
+    let variant_iter = stmt.query_map(params![parent_id], crate::map_product).map_err(|e| e.to_string())?;
+
+    let mut variants = Vec::new();
+    for variant in variant_iter {
+        variants.push(variant.map_err(|e| e.to_string())?);
+    }
+
+    Ok(variants)
+}
+
+#[tauri::command]
+pub fn create_parent_product(parent: ParentProductInput, db: State<Database>) -> Result<ParentProduct, String> {
+    validate_parent(&parent)?;
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let parent_id = Uuid::new_v4().to_string();
+    let attributes = serde_json::to_string(&clean_attributes(&parent.attributes)).map_err(|e| e.to_string())?;
+
+    conn.execute(
+        "INSERT INTO parent_products (id, name, category, base_price, tax_class, attributes, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)",
+        params![parent_id, parent.name.trim(), parent.category.trim(), parent.base_price, parent.tax_class.unwrap_or_else(|| "standard".to_string()), attributes, Utc::now().to_rfc3339()]
+    ).map_err(|e| e.to_string())?;
+
+    find_parent(&conn, &parent_id)
+}
+
+// Update the parent and carry its name, category, tax class and base price over
+// to the variants. Attribute values may be added, but values already used by a
+// variant cannot be removed.
+#[tauri::command]
+pub fn update_parent_product(parent_id: String, parent: ParentProductInput, db: State<Database>) -> Result<ParentProductDetail, String> {
+    validate_parent(&parent)?;
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    find_parent(&tx, &parent_id)?;
+
+    let attributes = clean_attributes(&parent.attributes);
+    let tax_class = parent.tax_class.unwrap_or_else(|| "standard".to_string());
+    tx.execute(
+        "UPDATE parent_products SET name = ?1, category = ?2, base_price = ?3, tax_class = ?4, attributes = ?5, updated_at = ?6 WHERE id = ?7",
+        params![parent.name.trim(), parent.category.trim(), parent.base_price, tax_class, serde_json::to_string(&attributes).map_err(|e| e.to_string())?, Utc::now().to_rfc3339(), parent_id]
+    ).map_err(|e| e.to_string())?;
+    let updated_parent = find_parent(&tx, &parent_id)?;
+
+    for variant in load_variants(&tx, &parent_id)? {
+        let values = variant.variant_attributes.clone().unwrap_or_default();
+        let still_defined = updated_parent.attributes.iter()
+            .all(|a| values.get(&a.name).map(|v| a.values.contains(v)).unwrap_or(false));
+        if !still_defined || values.len() != updated_parent.attributes.len() {
+            return Err(format!("{} uses an attribute value that is no longer defined", variant.name));
+        }
+
+        tx.execute(
+            "UPDATE products SET name = ?1, category = ?2, tax_class = ?3, price = COALESCE(price_override, ?4),
+                    price_changed_at = CASE WHEN price != COALESCE(price_override, ?4) THEN ?6 ELSE price_changed_at END
+             WHERE id = ?5",
+            params![variant_name(&updated_parent, &values), updated_parent.category, updated_parent.tax_class, updated_parent.base_price, variant.id, Utc::now().to_rfc3339()]
+        ).map_err(|e| e.to_string())?;
+    }
+
+    let variants = load_variants(&tx, &parent_id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(ParentProductDetail {
+        total_stock: variants.iter().map(|v| v.stock).sum(),
+        parent: updated_parent,
+        variants,
+    })
+}
+
+#[tauri::command]
+pub fn add_variant(parent_id: String, variant: VariantInput, db: State<Database>) -> Result<Product, String> {
+    let barcode = variant.barcode.trim().to_string();
+    if !barcode.is_empty() {
+        crate::barcodes::barcode_format(&barcode)?;
+    }
+    if variant.stock < 0 {
+        return Err("Stock cannot be negative".to_string());
+    }
+    if let Some(price) = variant.price_override {
+        if !price.is_finite() || price < 0.0 {
+            return Err("Price cannot be negative".to_string());
+        }
+    }
+    let mut conn = db.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let parent = find_parent(&tx, &parent_id)?;
+
+    let values: BTreeMap<String, String> = variant.attributes.iter()
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+    for attribute in &parent.attributes {
+        match values.get(&attribute.name) {
+            Some(value) if attribute.values.contains(value) => {},
+            Some(value) => return Err(format!("{} is not a valid {}", value, attribute.name)),
+            None => return Err(format!("{} is required", attribute.name)),
+        }
+    }
+    if values.len() != parent.attributes.len() {
+        return Err("Variant has attributes the parent does not define".to_string());
+    }
+
+    let attributes = serde_json::to_string(&values).map_err(|e| e.to_string())?;
+    let duplicate: i64 = tx.query_row(
+        "SELECT COUNT(*) FROM products WHERE parent_id = ?1 AND variant_attributes = ?2",
+        params![parent_id, attributes],
+        |row| row.get(0)
+    ).map_err(|e| e.to_string())?;
+    if duplicate > 0 {
+        return Err(format!("{} already exists", variant_name(&parent, &values)));
+    }
+
+    // Variants without a manufacturer barcode get an in-store one
+    let barcode = if barcode.is_empty() {
+        crate::barcodes::next_internal_barcode(&tx)?
+    } else if crate::barcodes::barcode_in_use(&tx, &barcode)? {
+        return Err(format!("Barcode {} is already in use", barcode));
+    } else {
+        barcode
+    };
+
+    let product_id = Uuid::new_v4().to_string();
+    tx.execute(
+        "INSERT INTO products (id, name, barcode, price, stock, category, tax_class, parent_id, variant_attributes, price_override, price_changed_at)
+         VALUES (?1, ?2, ?3, ?4, 0, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![product_id, variant_name(&parent, &values), barcode, variant.price_override.unwrap_or(parent.base_price), parent.category, parent.tax_class, parent_id, attributes, variant.price_override, Utc::now().to_rfc3339()]
+    ).map_err(|e| e.to_string())?;
+
+    if variant.stock > 0 {
+        crate::inventory::record_movement(&tx, &product_id, "adjustment", variant.stock, None, None, variant.user_id.as_deref(), Some("Opening balance"))?;
+    }
+
+    let product = tx.query_row(
+        &format!("SELECT {} FROM products WHERE id = ?1", crate::PRODUCT_COLUMNS),
+        params![product_id],
+        crate::map_product
+    ).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(product)
+}
+
+// Change or clear (None) a variant's own price
+#[tauri::command]
+pub fn set_variant_price(product_id: String, price_override: Option<f64>, db: State<Database>) -> Result<(), String> {
+    if let Some(price) = price_override {
+        if !price.is_finite() || price < 0.0 {
+            return Err("Price cannot be negative".to_string());
+        }
+    }
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let updated = conn.execute(
+        "UPDATE products SET price_override = ?1,
+                price = COALESCE(?1, (SELECT base_price FROM parent_products WHERE id = products.parent_id)),
+                price_changed_at = CASE WHEN price != COALESCE(?1, (SELECT base_price FROM parent_products WHERE id = products.parent_id))
+                                        THEN ?3 ELSE price_changed_at END
+         WHERE id = ?2 AND parent_id IS NOT NULL",
+        params![price_override, product_id, Utc::now().to_rfc3339()]
+    ).map_err(|e| e.to_string())?;
+
+    if updated == 0 {
+        return Err("Variant not found".to_string());
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_parent_products(db: State<Database>) -> Result<Vec<ParentProduct>, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM parent_products ORDER BY name", PARENT_COLUMNS)).map_err(|e| e.to_string())?;
+    let parent_iter = stmt.query_map([], map_parent).map_err(|e| e.to_string())?;
+
+    let mut parents = Vec::new();
+    for parent in parent_iter {
+        parents.push(decode_parent(parent.map_err(|e| e.to_string())?)?);
+    }
+
+    Ok(parents)
+}
+
+#[tauri::command]
+pub fn get_parent_product(parent_id: String, db: State<Database>) -> Result<ParentProductDetail, String> {
+    let conn = db.0.lock().map_err(|e| e.to_string())?;
+    let parent = find_parent(&conn, &parent_id)?;
+    let variants = load_variants(&conn, &parent_id)?;
+
+    Ok(ParentProductDetail {
+        total_stock: variants.iter().map(|v| v.stock).sum(),
+        parent,
+        variants,
+    })
+}
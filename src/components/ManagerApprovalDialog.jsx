@@ -0,0 +1,76 @@
+import React, { useState, useEffect } from 'react';
+import {
+  Dialog,
+  DialogContent,
+  DialogHeader,
+  DialogTitle,
+  DialogFooter,
+} from './ui/dialog';
+import { Button } from './ui/button';
+import { Input } from './ui/input';
+import { ShieldCheck } from 'lucide-react';
+
+// Asks a manager to sign off on an action (e.g. a discount above the approval
+// threshold); the credentials are checked by the backend with the action itself
+const ManagerApprovalDialog = ({ isOpen, message, onClose, onApprove }) => {
+  const [username, setUsername] = useState('');
+  const [password, setPassword] = useState('');
+
+  useEffect(() => {
+    if (isOpen) {
+      setUsername('');
+      setPassword('');
+    }
+  }, [isOpen]);
+
+  const handleSubmit = (event) => {
+    event.preventDefault();
+    if (!username.trim() || !password) return;
+    onApprove({ username: username.trim(), password });
+  };
+
+  return (
+    <Dialog open={isOpen} onOpenChange={onClose}>
+      <DialogContent className="sm:max-w-[400px] bg-card/95 backdrop-blur-sm border-border/50 rounded-2xl shadow-2xl">
+        <DialogHeader className="pb-4">
+          <div className="flex items-center gap-3">
+            <div className="w-10 h-10 bg-gradient-to-br from-primary to-primary/70 rounded-xl flex items-center justify-center shadow-lg">
+              <ShieldCheck className="w-6 h-6 text-white" />
+            </div>
+            <DialogTitle className="text-2xl font-bold text-foreground">
+              Manager Approval
+            </DialogTitle>
+          </div>
+        </DialogHeader>
+
+        <form onSubmit={handleSubmit} className="grid gap-4">
+          <p className="text-sm text-muted-foreground">{message}</p>
+          <Input
+            type="text"
+            value={username}
+            onChange={(e) => setUsername(e.target.value)}
+            placeholder="Manager username"
+            autoFocus
+          />
+          <Input
+            type="password"
+            value={password}
+            onChange={(e) => setPassword(e.target.value)}
+            placeholder="Password"
+          />
+
+          <DialogFooter className="flex justify-between gap-4 pt-2">
+            <Button type="button" variant="outline" onClick={onClose}>
+              Cancel
+            </Button>
+            <Button type="submit" disabled={!username.trim() || !password}>
+              Approve
+            </Button>
+          </DialogFooter>
+        </form>
+      </DialogContent>
+    </Dialog>
+  );
+};
+
+export default ManagerApprovalDialog;
@@ -13,8 +13,11 @@ import {
   CreditCard
 } from 'lucide-react';
 import PaymentModal from './PaymentModal';
+import ManagerApprovalDialog from './ManagerApprovalDialog';
+import { useAuth } from '../contexts/AuthContext';
 
 const POSInterface = ({ products, onProductsUpdate, theme }) => {
+  const { currentShift } = useAuth();
   const [cart, setCart] = useState([]);
   const [barcode, setBarcode] = useState('');
   const [searchTerm, setSearchTerm] = useState('');
@@ -22,6 +25,10 @@ const POSInterface = ({ products, onProductsUpdate, theme }) => {
   const [isProcessing, setIsProcessing] = useState(false);
   const [showPaymentModal, setShowPaymentModal] = useState(false);
   const [lastAddedItem, setLastAddedItem] = useState(null);
+  // Payment waiting for a manager to approve its discount
+  const [pendingApproval, setPendingApproval] = useState(null);
+  // Cart as priced by the backend: promotions, tax and cash rounding included
+  const [pricedCart, setPricedCart] = useState(null);
   
   const barcodeInputRef = useRef(null);
   const searchInputRef = useRef(null);
@@ -81,47 +88,69 @@ const POSInterface = ({ products, onProductsUpdate, theme }) => {
     return () => document.removeEventListener('keydown', handleKeyDown);
   }, [cart.length, showPaymentModal]);
 
+  // Units of stock a cart line takes: grams for products sold by weight,
+  // otherwise items times the pack size of the scanned barcode
+  const stockUnits = (line) => line.sold_by_weight
+    ? Math.round(line.quantity * 1000)
+    : line.quantity * (line.pack_quantity || 1);
+
+  // Stock still free for a product once the other cart lines are taken out
+  const availableStock = (cartLines, productId, stock, exceptKey = null) => stock - cartLines
+    .filter(item => item.product_id === productId && item.key !== exceptKey)
+    .reduce((units, item) => units + stockUnits(item), 0);
+
   const addProductToCart = (product, quantity = 1) => {
-    if (product.stock < quantity) {
-      alert(`Insufficient stock! Only ${product.stock} items available.`);
-      return;
-    }
+    // Scale labels carry their own weight and are never merged into another line
+    const isScaleLabel = product.scale_quantity != null;
+    const lineQuantity = isScaleLabel ? product.scale_quantity : quantity;
+    // Price labels are charged the amount printed on them
+    const lineSubtotal = product.scale_amount ?? lineQuantity * product.price;
+    const line = {
+      product_id: product.id,
+      barcode: product.barcode,
+      pack_quantity: product.pack_quantity || 1,
+      sold_by_weight: product.sold_by_weight,
+      quantity: lineQuantity
+    };
 
     setCart(prevCart => {
-      const existingItem = prevCart.find(item => item.product_id === product.id);
-      
+      // Singles and packs of the same product are separate lines
+      const key = isScaleLabel
+        ? `${product.id}|${product.barcode}|${Date.now()}`
+        : `${product.id}|${product.barcode}`;
+      const existingItem = prevCart.find(item => item.key === key);
+      const needed = stockUnits({ ...line, quantity: (existingItem?.quantity || 0) + lineQuantity });
+
+      if (needed > availableStock(prevCart, product.id, product.stock, key)) {
+        alert(`Insufficient stock for ${product.name}!`);
+        return prevCart;
+      }
+
+      setLastAddedItem(product);
       if (existingItem) {
-        // Check if new quantity would exceed stock
-        if (existingItem.quantity + quantity > product.stock) {
-          alert(`Insufficient stock! Only ${product.stock - existingItem.quantity} more items available.`);
-          return prevCart;
-        }
-        
-        const updatedCart = prevCart.map(item =>
-          item.product_id === product.id
+        return prevCart.map(item =>
+          item.key === key
             ? { 
                 ...item, 
-                quantity: item.quantity + quantity, 
-                subtotal: (item.quantity + quantity) * item.price,
-                total: (item.quantity + quantity) * item.price - (item.discount || 0)
+                quantity: item.quantity + lineQuantity, 
+                subtotal: (item.quantity + lineQuantity) * item.price,
+                total: (item.quantity + lineQuantity) * item.price - (item.discount || 0)
               }
             : item
         );
-        setLastAddedItem(product);
-        return updatedCart;
-      } else {
-        const newItem = {
-          product_id: product.id,
-          name: product.name,
-          price: product.price,
-          quantity: quantity,
-          discount: 0,
-          subtotal: quantity * product.price,
-          total: quantity * product.price
-        };
-        setLastAddedItem(product);
-        return [...prevCart, newItem];
       }
+
+      const newItem = {
+        ...line,
+        key,
+        scale_label: isScaleLabel,
+        name: product.name,
+        price: product.price,
+        discount: 0,
+        subtotal: lineSubtotal,
+        total: lineSubtotal
+      };
+      return [...prevCart, newItem];
     });
   };
 
@@ -146,23 +175,23 @@ const POSInterface = ({ products, onProductsUpdate, theme }) => {
     }
   };
 
-  const removeFromCart = (productId) => {
-    setCart(prevCart => prevCart.filter(item => item.product_id !== productId));
+  const removeFromCart = (key) => {
+    setCart(prevCart => prevCart.filter(item => item.key !== key));
   };
 
-  const updateCartQuantity = (productId, newQuantity) => {
+  const updateCartQuantity = (key, newQuantity) => {
     if (newQuantity <= 0) {
-      removeFromCart(productId);
+      removeFromCart(key);
       return;
     }
 
     setCart(prevCart => 
       prevCart.map(item => {
-        if (item.product_id === productId) {
+        if (item.key === key) {
           // Find product to check stock
-          const product = products.find(p => p.id === productId);
-          if (product && newQuantity > product.stock) {
-            alert(`Insufficient stock! Only ${product.stock} items available.`);
+          const product = products.find(p => p.id === item.product_id);
+          if (product && stockUnits({ ...item, quantity: newQuantity }) > availableStock(prevCart, item.product_id, product.stock, key)) {
+            alert(`Insufficient stock for ${item.name}!`);
             return item;
           }
           const newSubtotal = newQuantity * item.price;
@@ -178,10 +207,10 @@ const POSInterface = ({ products, onProductsUpdate, theme }) => {
     );
   };
 
-  const updateCartDiscount = (productId, newDiscount) => {
+  const updateCartDiscount = (key, newDiscount) => {
     setCart(prevCart => 
       prevCart.map(item => {
-        if (item.product_id === productId) {
+        if (item.key === key) {
           const discount = Math.min(newDiscount, item.subtotal);
           return { 
             ...item, 
@@ -194,7 +223,37 @@ const POSInterface = ({ products, onProductsUpdate, theme }) => {
     );
   };
 
+  // Cart lines as sent to the backend; the approval goes on line `approvalLine`
+  const saleItems = (approval = null, approvalLine = null) =>
+    cart.map(({ key, scale_label, sold_by_weight, ...item }, index) => ({
+      ...item,
+      line_discount: item.discount > 0
+        ? { discount_type: 'fixed', value: item.discount, reason: null, approval: index === approvalLine ? approval : null }
+        : null
+    }));
+
+  const priceCart = (discount = null) =>
+    invoke('price_cart', { items: saleItems(), discount, paymentMethod });
+
+  useEffect(() => {
+    if (cart.length === 0) {
+      setPricedCart(null);
+      return;
+    }
+
+    let cancelled = false;
+    priceCart()
+      .then(priced => { if (!cancelled) setPricedCart(priced); })
+      .catch(error => {
+        console.error('Error pricing cart:', error);
+        if (!cancelled) setPricedCart(null);
+      });
+    return () => { cancelled = true; };
+  }, [cart, paymentMethod]);
+
+  // What the sale will charge; the line sum is only shown until the backend answers
   const getCartTotal = () => {
+    if (pricedCart) return pricedCart.amount_due;
     return cart.reduce((total, item) => total + item.total, 0);
   };
 
@@ -212,19 +271,30 @@ const POSInterface = ({ products, onProductsUpdate, theme }) => {
     );
   };
 
-  const handleProcessPayment = async (paymentData) => {
+  const handleProcessPayment = async (paymentData, approval = null) => {
     setIsProcessing(true);
 
+    // One manager approval covers every discount on the sale, so it goes on
+    // the order discount, or on the first discounted line without one
+    const approvalLine = paymentData.discountValue > 0 ? null : cart.findIndex(item => item.discount > 0);
+
     try {
       // Create transaction
       const transactionId = await invoke('create_transaction', {
-        items: cart,
+        items: saleItems(approval, approvalLine),
         paymentMethod: paymentMethod,
-        discountData: {
-          type: paymentData.discountType,
-          value: paymentData.discountValue,
-          discountedTotal: paymentData.discountedTotal
-        }
+        payments: paymentMethod === 'cash' && paymentData.paymentAmount > 0
+          ? [{ method: 'cash', amount: paymentData.paymentAmount, reference: null }]
+          : null,
+        shiftId: currentShift?.id ?? null,
+        discount: paymentData.discountValue > 0
+          ? {
+              discount_type: paymentData.discountType,
+              value: paymentData.discountValue,
+              reason: null,
+              approval
+            }
+          : null
       });
 
       // Print receipt
@@ -253,7 +323,11 @@ const POSInterface = ({ products, onProductsUpdate, theme }) => {
       alert(`Transaction completed! Receipt ID: ${transactionId}`);
     } catch (error) {
       console.error('Error processing transaction:', error);
-      alert('Error processing transaction');
+      if (String(error).includes('requires manager approval')) {
+        setPendingApproval({ paymentData, message: String(error) });
+      } else {
+        alert(approval ? String(error) : 'Error processing transaction');
+      }
     } finally {
       setIsProcessing(false);
     }
@@ -450,13 +524,14 @@ const POSInterface = ({ products, onProductsUpdate, theme }) => {
                   </thead>
                   <tbody>
                     {cart.map((item, index) => (
-                      <tr key={item.product_id} className={`border-b border-border/30 hover:bg-primary/5 transition-colors duration-200 ${index % 2 === 0 ? 'bg-background/30' : 'bg-background/10'}`}>
+                      <tr key={item.key} className={`border-b border-border/30 hover:bg-primary/5 transition-colors duration-200 ${index % 2 === 0 ? 'bg-background/30' : 'bg-background/10'}`}>
                         <td className="p-4">
                           <div className="flex items-center gap-2">
                             <Button
                               variant="outline"
                               size="sm"
-                              onClick={() => updateCartQuantity(item.product_id, item.quantity - 1)}
+                              onClick={() => updateCartQuantity(item.key, item.quantity - 1)}
+                              disabled={item.scale_label}
                               className="h-8 w-8 p-0 hover:bg-destructive hover:text-destructive-foreground transition-all duration-200"
                             >
                               −
@@ -467,7 +542,8 @@ const POSInterface = ({ products, onProductsUpdate, theme }) => {
                             <Button
                               variant="outline"
                               size="sm"
-                              onClick={() => updateCartQuantity(item.product_id, item.quantity + 1)}
+                              onClick={() => updateCartQuantity(item.key, item.quantity + 1)}
+                              disabled={item.scale_label}
                               className="h-8 w-8 p-0 hover:bg-green-500 hover:text-white transition-all duration-200"
                             >
                               +
@@ -489,7 +565,7 @@ const POSInterface = ({ products, onProductsUpdate, theme }) => {
                             <Input
                               type="number"
                               value={item.discount || 0}
-                              onChange={(e) => updateCartDiscount(item.product_id, parseFloat(e.target.value) || 0)}
+                              onChange={(e) => updateCartDiscount(item.key, parseFloat(e.target.value) || 0)}
                               placeholder="0"
                               min="0"
                               max={item.subtotal}
@@ -504,7 +580,7 @@ const POSInterface = ({ products, onProductsUpdate, theme }) => {
                           <Button
                             variant="destructive"
                             size="sm"
-                            onClick={() => removeFromCart(item.product_id)}
+                            onClick={() => removeFromCart(item.key)}
                             className="hover:bg-red-600 transition-all duration-200 hover:scale-105"
                           >
                             <Trash2 className="w-4 h-4 mr-2" />
@@ -593,10 +669,22 @@ const POSInterface = ({ products, onProductsUpdate, theme }) => {
         isOpen={showPaymentModal}
         onClose={() => setShowPaymentModal(false)}
         total={getCartTotal()}
+        priceCart={priceCart}
         paymentMethod={paymentMethod}
         onPaymentMethodChange={setPaymentMethod}
         onProcessPayment={handleProcessPayment}
       />
+
+      <ManagerApprovalDialog
+        isOpen={pendingApproval !== null}
+        message={pendingApproval?.message}
+        onClose={() => setPendingApproval(null)}
+        onApprove={(approval) => {
+          const { paymentData } = pendingApproval;
+          setPendingApproval(null);
+          handleProcessPayment(paymentData, approval);
+        }}
+      />
     </div>
   );
 };
@@ -22,6 +22,7 @@ const PaymentModal = ({
   isOpen, 
   onClose, 
   total, 
+  priceCart,
   onProcessPayment, 
   paymentMethod = 'cash',
   onPaymentMethodChange 
@@ -44,27 +45,30 @@ const PaymentModal = ({
   }, [isOpen, total]);
 
   useEffect(() => {
-    // Calculate discounted total
-    let discount = 0;
-    if (discountType === 'percentage') {
-      discount = (discountValue / 100) * total;
-    } else {
-      discount = Math.min(discountValue, total);
-    }
-    
-    const newDiscountedTotal = Math.max(0, total - discount);
-    setDiscountedTotal(newDiscountedTotal);
-    
+    if (!isOpen) return;
+
+    // The discounted total comes from the backend, priced as the sale will be
+    let cancelled = false;
+    const discount = discountValue > 0
+      ? { discount_type: discountType, value: discountValue, reason: null, approval: null }
+      : null;
+    priceCart(discount)
+      .then(priced => { if (!cancelled) setDiscountedTotal(priced.amount_due); })
+      .catch(error => console.error('Error pricing cart:', error));
+    return () => { cancelled = true; };
+  }, [isOpen, discountType, discountValue, paymentMethod, total]);
+
+  useEffect(() => {
     // Calculate change or shortage
     const payment = parseFloat(paymentAmount) || 0;
-    if (payment >= newDiscountedTotal) {
-      setChange(payment - newDiscountedTotal);
+    if (payment >= discountedTotal) {
+      setChange(payment - discountedTotal);
       setShortage(0);
     } else {
       setChange(0);
-      setShortage(newDiscountedTotal - payment);
+      setShortage(discountedTotal - payment);
     }
-  }, [discountType, discountValue, paymentAmount, total]);
+  }, [paymentAmount, discountedTotal]);
 
   const handleProcessPayment = () => {
     if (parseFloat(paymentAmount) < discountedTotal) {